@@ -1,8 +1,9 @@
 // use byte_string::ByteStr;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::str;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Item(u8);
 
 impl Item {
@@ -57,6 +58,148 @@ impl Rucksack {
     fn priority(&self) -> u8 {
         self.overlap().unwrap_or(&Item(97)).priority()
     }
+
+    /// Number of each item type in the (left, right) compartments
+    fn counts(&self) -> BTreeMap<Item, (usize, usize)> {
+        let left = self.0.len();
+        self.consolidate().into_iter().enumerate()
+            .fold(BTreeMap::new(), |mut counts, (i, item)| {
+                let count = counts.entry(item).or_insert((0, 0));
+                if i < left { count.0 += 1 } else { count.1 += 1 }
+                counts
+            })
+    }
+
+    /// Cheapest set of moves after which no item type is in both compartments,
+    /// with both compartments still the same size. None if no such repacking exists.
+    ///
+    /// Every item type has to end up wholly on one side, so this is a knapsack:
+    /// pick the types for the left compartment so that they fill exactly half
+    /// the rucksack, paying one move for every item that has to cross over.
+    fn repack(&self) -> Option<Vec<Move>> {
+        if self.overlap().is_none() {
+            return Some(Vec::new());
+        }
+        let half = self.0.len();
+        let types: Vec<(Item, (usize, usize))> = self.counts().into_iter().collect();
+
+        // costs[t][s]: fewest moves placing the first t types with s items on the left
+        let mut costs: Vec<Vec<Option<usize>>> = vec![
+            (0..=half).map(|s| if s == 0 { Some(0) } else { None }).collect()
+        ];
+        for &(_, (l, r)) in types.iter() {
+            let prev = costs.last().unwrap();
+            let next = (0..=half).map(|s| {
+                let right = prev[s].map(|c| c + l);
+                let left = s.checked_sub(l + r).and_then(|p| prev[p]).map(|c| c + r);
+                match (left, right) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                }
+            }).collect();
+            costs.push(next);
+        }
+        costs.last().unwrap()[half]?;
+
+        // walk back through the table to find which side each type went to
+        let mut moves = Vec::new();
+        let mut s = half;
+        for (t, &(item, (l, r))) in types.iter().enumerate().rev() {
+            let cost = costs[t + 1][s].unwrap();
+            if costs[t][s].map(|c| c + l) == Some(cost) {
+                if l > 0 {
+                    moves.push(Move { item, count: l, to: Compartment::Right });
+                }
+            } else {
+                if r > 0 {
+                    moves.push(Move { item, count: r, to: Compartment::Left });
+                }
+                s -= l + r;
+            }
+        }
+        moves.reverse();
+        Some(moves)
+    }
+
+    #[cfg(test)]
+    fn apply(&self, moves: &[Move]) -> Rucksack {
+        moves.iter().fold(self.clone(), |Rucksack(mut left, mut right), m| {
+            let (from, to) = match m.to {
+                Compartment::Left => (&mut right, &mut left),
+                Compartment::Right => (&mut left, &mut right),
+            };
+            for _ in 0..m.count {
+                let i = from.iter().position(|&i| i == m.item)
+                    .unwrap_or_else(|| panic!("No {:?} left to move to {:?}", m.item, m.to));
+                to.push(from.remove(i));
+            }
+            Rucksack(left, right)
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Compartment {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Move {
+    item: Item,
+    count: usize,
+    to: Compartment,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} {} to the {:?}", self.count, self.item.0 as char, self.to)
+    }
+}
+
+/// One rucksack's repacking, for printing
+struct Plan<'a>(&'a Option<Vec<Move>>);
+
+impl fmt::Display for Plan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "impossible"),
+            Some(moves) if moves.is_empty() => write!(f, "already fine"),
+            Some(moves) => write!(f, "{}", moves.iter()
+                .map(|m| m.to_string()).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct RepackSummary {
+    untouched: usize,
+    repacked: usize,
+    impossible: usize,
+    moves: usize,
+}
+
+impl From<&[Option<Vec<Move>>]> for RepackSummary {
+    fn from(plans: &[Option<Vec<Move>>]) -> RepackSummary {
+        plans.iter().fold(RepackSummary::default(), |mut summary, plan| {
+            match plan {
+                None => summary.impossible += 1,
+                Some(moves) if moves.is_empty() => summary.untouched += 1,
+                Some(moves) => {
+                    summary.repacked += 1;
+                    summary.moves += moves.iter().map(|m| m.count).sum::<usize>();
+                },
+            }
+            summary
+        })
+    }
+}
+
+impl fmt::Display for RepackSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Already fine: {}; repacked: {} ({} items moved); impossible: {}",
+            self.untouched, self.repacked, self.moves, self.impossible)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -93,6 +236,89 @@ impl Group {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct ItemStats {
+    count: usize,
+    sacks: usize,
+    shared: usize,
+    badges: usize,
+}
+
+impl ItemStats {
+    /// What this item type adds to both priority sums
+    fn priority(&self, item: &Item) -> u32 {
+        (self.shared + self.badges) as u32 * item.priority() as u32
+    }
+}
+
+/// How often each item type turns up across every rucksack and group
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Frequencies(BTreeMap<Item, ItemStats>);
+
+impl From<&[Group]> for Frequencies {
+    fn from(groups: &[Group]) -> Frequencies {
+        let mut freqs = BTreeMap::<Item, ItemStats>::new();
+        for group in groups {
+            for (sack, items) in group.0.iter().zip(group.consolidate()) {
+                let mut seen = Vec::new();
+                for item in items {
+                    freqs.entry(item).or_default().count += 1;
+                    if !seen.contains(&item) {
+                        seen.push(item);
+                        freqs.entry(item).or_default().sacks += 1;
+                    }
+                }
+                if let Some(&item) = sack.overlap() {
+                    freqs.entry(item).or_default().shared += 1;
+                }
+            }
+            if let Some(item) = group.badge() {
+                freqs.entry(item).or_default().badges += 1;
+            }
+        }
+        Frequencies(freqs)
+    }
+}
+
+impl Frequencies {
+    /// The n items most often found in both compartments, most common first
+    fn most_shared(&self, n: usize) -> Vec<(Item, usize)> {
+        self.top(n, |stats| stats.shared)
+    }
+
+    /// The n items most often used as a badge, most common first
+    fn most_badges(&self, n: usize) -> Vec<(Item, usize)> {
+        self.top(n, |stats| stats.badges)
+    }
+
+    fn top(&self, n: usize, key: fn(&ItemStats) -> usize) -> Vec<(Item, usize)> {
+        let mut items: Vec<(Item, usize)> = self.0.iter()
+            .map(|(&item, stats)| (item, key(stats)))
+            .filter(|&(_, k)| k > 0)
+            .collect();
+        // stable sort, so ties stay in item order
+        items.sort_by_key(|&(_, k)| std::cmp::Reverse(k));
+        items.truncate(n);
+        items
+    }
+
+    fn priority(&self) -> u32 {
+        self.0.iter().map(|(item, stats)| stats.priority(item)).sum()
+    }
+}
+
+impl fmt::Display for Frequencies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Item | Count | Sacks | Shared | Badges | Priority")?;
+        for (item, stats) in self.0.iter() {
+            writeln!(f, "{:>4} | {:>5} | {:>5} | {:>6} | {:>6} | {:>8}",
+                item.0 as char, stats.count, stats.sacks, stats.shared, stats.badges,
+                stats.priority(item))?;
+        }
+        write!(f, "Total priority: {}", self.priority())
+    }
+}
+
 fn get_groups(sacks: Vec<Rucksack>) -> Vec<Group> {
     let l = sacks.len();
     let is = match l % 3 {
@@ -120,11 +346,26 @@ fn main() {
 
     println!("Sum of priorities: {}", sum_of_priorities);
 
+    let plans: Vec<Option<Vec<Move>>> = rucksacks.iter()
+        .map(|rucksack| rucksack.repack()).collect();
+    for (k, plan) in plans.iter().enumerate() {
+        println!("Rucksack {}: {}", k + 1, Plan(plan));
+    }
+    println!("Repacking: {}", RepackSummary::from(plans.as_slice()));
+
     let groups = get_groups(rucksacks);
     let sum_of_badge_priorities = groups.iter()
         .map(|g| g.priority() as u32).sum::<u32>();
 
     println!("Sum of badge priorities: {}", sum_of_badge_priorities);
+
+    let frequencies = Frequencies::from(groups.as_slice());
+    let show = |top: Vec<(Item, usize)>| top.iter()
+        .map(|(item, n)| format!("{} ({})", item.0 as char, n))
+        .collect::<Vec<_>>().join(", ");
+    println!("\n{}", frequencies);
+    println!("Most shared items: {}", show(frequencies.most_shared(5)));
+    println!("Most common badges: {}", show(frequencies.most_badges(5)));
 }
 
 #[cfg(test)]
@@ -190,4 +431,69 @@ mod tests {
         let prios = vec![18, 52];
         assert_eq!(prios, groups.iter().map(|g| g.priority()).collect::<Vec<u8>>())
     }
+
+    #[test]
+    fn check_example_frequencies() {
+        let groups = get_groups(example_rucksacks());
+        let frequencies = Frequencies::from(groups.as_slice());
+
+        assert_eq!(Some(&ItemStats { count: 3, sacks: 2, shared: 1, badges: 0 }),
+                   frequencies.0.get(&Item(b'p')));
+        assert_eq!(Some(&ItemStats { count: 7, sacks: 4, shared: 0, badges: 1 }),
+                   frequencies.0.get(&Item(b'r')));
+        assert_eq!(157 + 70, frequencies.priority());
+        assert_eq!(example_contents().iter().map(|s| s.len()).sum::<usize>(),
+                   frequencies.0.values().map(|stats| stats.count).sum::<usize>());
+    }
+
+    #[test]
+    fn check_most_common() {
+        let groups = get_groups(example_rucksacks());
+        let frequencies = Frequencies::from(groups.as_slice());
+
+        assert_eq!(vec![(Item(b'L'), 1), (Item(b'P'), 1)], frequencies.most_shared(2));
+        assert_eq!(vec![(Item(b'Z'), 1), (Item(b'r'), 1)], frequencies.most_badges(5));
+    }
+
+    fn rucksack(s: &str) -> Rucksack {
+        (&s.bytes().map(Item).collect::<Vec<Item>>()).into()
+    }
+
+    #[test]
+    fn check_example_repacking() {
+        let example_rucksacks = example_rucksacks();
+        let plans: Vec<Option<Vec<Move>>> = example_rucksacks.iter()
+            .map(|rucksack| rucksack.repack()).collect();
+        for (sack, plan) in example_rucksacks.iter().zip(plans.iter()) {
+            let repacked = sack.apply(plan.as_ref().unwrap());
+            assert_eq!(None, repacked.overlap());
+            assert_eq!(repacked.0.len(), repacked.1.len());
+        }
+        assert_eq!(RepackSummary { untouched: 0, repacked: 6, impossible: 0, moves: 18 },
+                   RepackSummary::from(plans.as_slice()));
+    }
+
+    #[test]
+    fn check_minimal_repacking() {
+        // the lone a on the right comes over, and the b makes room for it
+        assert_eq!(Some(vec![
+            Move { item: Item(b'a'), count: 1, to: Compartment::Left },
+            Move { item: Item(b'b'), count: 1, to: Compartment::Right },
+        ]), rucksack("aabcac").repack());
+        assert_eq!(Some(vec![]), rucksack("abcdef").repack());
+    }
+
+    #[test]
+    fn check_impossible_repacking() {
+        assert_eq!(None, rucksack("aaab").repack());
+        assert_eq!(None, rucksack("aaaabb").repack());
+    }
+
+    #[test]
+    fn check_plan_output() {
+        assert_eq!("move 1 a to the Left, move 1 b to the Right",
+                   Plan(&rucksack("aabcac").repack()).to_string());
+        assert_eq!("already fine", Plan(&rucksack("abcdef").repack()).to_string());
+        assert_eq!("impossible", Plan(&rucksack("aaab").repack()).to_string());
+    }
 }
@@ -1,23 +1,917 @@
+use std::collections::{BTreeSet, BinaryHeap};
+use std::fmt;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use useful::{Interval, ParseIntervalError};
+
+/// Axis-aligned box of sections, one interval per dimension, written "2-4x6-8"
+///
+/// Plain "a-b" assignments are the 1-D case.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Region(Vec<Interval>);
+
+impl FromStr for Region {
+    type Err = ParseIntervalError;
+
+    fn from_str(s: &str) -> Result<Region, ParseIntervalError> {
+        s.split('x').map(|axis| axis.parse()).collect::<Result<_, _>>().map(Region)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let axes: Vec<String> = self.0.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}", axes.join("x"))
+    }
+}
+
+impl Region {
+    /// The assignment as a plain range of sections, if it is one
+    fn interval(&self) -> Option<Interval> {
+        match self.0.as_slice() {
+            &[i] => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Pairs of intervals along each axis; `parse` makes sure every assignment
+    /// has the same number of them
+    fn axes<'a>(&'a self, other: &'a Region) -> impl Iterator<Item = (&'a Interval, &'a Interval)> {
+        debug_assert_eq!(self.0.len(), other.0.len(), "{} and {} differ in dimension", self, other);
+        self.0.iter().zip(other.0.iter())
+    }
+
+    fn covers(&self, other: &Region) -> bool {
+        self.axes(other).all(|(a, b)| a.covers(b))
+    }
+
+    fn overlaps(&self, other: &Region) -> bool {
+        self.axes(other).all(|(a, b)| a.overlaps(b))
+    }
+}
+
+/// What's wrong with an assignment, by 1-based line
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ParseError {
+    Interval { line: usize, error: ParseIntervalError },
+    /// Assignments of a different dimension than the first one in the file
+    MixedDimensions { line: usize, region: Region, expected: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Interval { line, error } => write!(f, "Line {}: {}", line, error),
+            ParseError::MixedDimensions { line, region, expected } => {
+                write!(f, "Line {}: {}-D assignment {} among {}-D ones",
+                    line, region.0.len(), region, expected)
+            },
+        }
+    }
+}
+
+/// Every line lists the assignments of any number of elves, separated by ','
+///
+/// All assignments must have the same number of dimensions, so any two can be compared.
+fn parse(contents: &str) -> Result<Vec<Vec<Region>>, ParseError> {
+    let mut dimensions = None;
+    contents.lines().enumerate()
+        .map(|(k, s)| s.split(',').map(|a| {
+            let region: Region = a.parse()
+                .map_err(|error| ParseError::Interval { line: k + 1, error })?;
+            match *dimensions.get_or_insert(region.0.len()) {
+                expected if expected != region.0.len() => {
+                    Err(ParseError::MixedDimensions { line: k + 1, region, expected })
+                },
+                _ => Ok(region),
+            }
+        }).collect())
+        .collect()
+}
+
+/// An elf, identified by its line in the input and its place on that line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Elf {
+    line: usize,
+    slot: usize,
+}
+
+impl fmt::Display for Elf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} elf {}", self.line + 1, self.slot + 1)
+    }
+}
+
+fn elves(lines: &[Vec<Region>]) -> Vec<(Elf, Region)> {
+    lines.iter().enumerate()
+        .flat_map(|(line, assignments)| assignments.iter().enumerate()
+            .map(move |(slot, a)| (Elf { line, slot }, a.clone())))
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Overlap {
+    /// One assignment lies entirely within the other
+    Full,
+    Partial,
+}
+
+/// Every pair of elves, on any lines, whose assignments overlap
+///
+/// Sweeps over the assignments by start section, keeping the ones that haven't
+/// ended yet, so this takes O(n log n) plus the number of pairs found. Boxes are
+/// swept along their first axis and then checked along the others.
+fn overlapping_pairs(elves: &[(Elf, Region)]) -> Vec<(Elf, Elf, Overlap)> {
+    let mut order: Vec<usize> = (0..elves.len()).collect();
+    order.sort_by_key(|&k| elves[k].1 .0[0].start);
+
+    // (end section, index) of every assignment the sweep is currently inside
+    let mut active: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let mut pairs = Vec::new();
+    for k in order {
+        let (elf, a) = (elves[k].0, &elves[k].1);
+        while let Some(&(end, j)) = active.first() {
+            if end >= a.0[0].start {
+                break;
+            }
+            active.remove(&(end, j));
+        }
+        for &(_, j) in active.iter() {
+            let (other, b) = (elves[j].0, &elves[j].1);
+            if !a.overlaps(b) {
+                continue;
+            }
+            let overlap = match a.covers(b) || b.covers(a) {
+                true => Overlap::Full,
+                false => Overlap::Partial,
+            };
+            pairs.push((elf.min(other), elf.max(other), overlap));
+        }
+        active.insert((a.0[0].end, k));
+    }
+    pairs.sort_by_key(|&(a, b, _)| (a, b));
+    pairs
+}
+
+/// Which members of one line contain or overlap each other, by slot
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct LineReport {
+    /// (outer, inner) slots
+    containments: Vec<(usize, usize)>,
+    overlaps: Vec<(usize, usize)>,
+}
+
+impl LineReport {
+    fn new(line: &[Region]) -> LineReport {
+        let elves: Vec<(Elf, Region)> = line.iter().enumerate()
+            .map(|(slot, a)| (Elf { line: 0, slot }, a.clone()))
+            .collect();
+        overlapping_pairs(&elves).into_iter()
+            .fold(LineReport::default(), |mut report, (a, b, overlap)| {
+                report.overlaps.push((a.slot, b.slot));
+                if overlap == Overlap::Full {
+                    report.containments.push(match line[a.slot].covers(&line[b.slot]) {
+                        true => (a.slot, b.slot),
+                        false => (b.slot, a.slot),
+                    });
+                }
+                report
+            })
+    }
+
+    /// Whether an elf's whole assignment is also someone else's work (part 1)
+    fn has_containment(&self) -> bool {
+        !self.containments.is_empty()
+    }
+
+    /// Whether any two elves share a section (part 2)
+    fn has_overlap(&self) -> bool {
+        !self.overlaps.is_empty()
+    }
+}
+
+impl fmt::Display for LineReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |pairs: &[(usize, usize)], verb: &str| {
+            pairs.iter()
+                .map(|(a, b)| format!("elf {} {} elf {}", a + 1, verb, b + 1))
+                .collect::<Vec<_>>()
+        };
+        let parts = [list(&self.containments, "contains"), list(&self.overlaps, "overlaps")].concat();
+        match parts.is_empty() {
+            true => write!(f, "no overlaps"),
+            false => write!(f, "{}", parts.join("; ")),
+        }
+    }
+}
+
+/// How all assignments together cover the sections between the first and last one
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Coverage {
+    covered: usize,
+    gaps: Vec<Interval>,
+    max_elves: usize,
+    busiest: Vec<Interval>,
+}
+
+impl From<&[Interval]> for Coverage {
+    /// Sweeps over the sorted interval endpoints, keeping count of how many
+    /// assignments the current stretch of sections lies in
+    fn from(assignments: &[Interval]) -> Coverage {
+        let mut events: Vec<(usize, isize)> = assignments.iter()
+            .flat_map(|i| [(i.start, 1), (i.end + 1, -1)])
+            .collect();
+        events.sort_unstable();
+
+        let mut coverage = Coverage { covered: 0, gaps: Vec::new(), max_elves: 0, busiest: Vec::new() };
+        let mut elves = 0;
+        for (k, &(pos, change)) in events.iter().enumerate() {
+            elves = (elves as isize + change) as usize;
+            let next = match events.get(k + 1) {
+                Some(&(next, _)) if next > pos => next,
+                _ => continue, // more events here, or the end of the sweep
+            };
+            let stretch = Interval::new(pos, next - 1);
+            match elves {
+                0 => coverage.gaps.push(stretch),
+                _ => coverage.covered += stretch.length(),
+            }
+            if elves > coverage.max_elves {
+                coverage.max_elves = elves;
+                coverage.busiest.clear();
+            }
+            if elves == coverage.max_elves && elves > 0 {
+                match coverage.busiest.last_mut().and_then(|last| last.union(&stretch)) {
+                    Some(joined) => *coverage.busiest.last_mut().unwrap() = joined,
+                    None => coverage.busiest.push(stretch),
+                }
+            }
+        }
+        coverage
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |is: &[Interval]| {
+            is.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ")
+        };
+        write!(f, "Sections covered: {}\nUncovered gaps: [{}]\nMost elves on one section: {} (sections {})",
+            self.covered, list(&self.gaps), self.max_elves, list(&self.busiest))
+    }
+}
+
+/// Assignments moved apart so that none overlap, each keeping its length
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Reassignment {
+    assignments: Vec<Interval>,
+    /// Total distance moved by all start and end sections
+    change: usize,
+    /// Whether no other reassignment changes less
+    exact: bool,
+}
+
+impl fmt::Display for Reassignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.exact {
+            true => write!(f, "the smallest total change, {}", self.change),
+            false => write!(f, "a total change of {}, which may not be the smallest", self.change),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ReassignError {
+    NotOneDimensional(Region),
+    NoRoom { needed: usize, available: Interval },
+}
+
+impl fmt::Display for ReassignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReassignError::NotOneDimensional(r) => {
+                write!(f, "Only 1-D assignments can be reassigned, not {}", r)
+            },
+            ReassignError::NoRoom { needed, available } => {
+                write!(f, "Assignments need {} sections, but only {} ({}) are available",
+                    needed, available.length(), available)
+            },
+        }
+    }
+}
+
+/// Up to this many assignments are tried in every order; more keep the order of their midpoints
+const EXHAUSTIVE_REASSIGN: usize = 6;
+
+/// Smallest total change to the assignments' endpoints, keeping every length
+/// and staying `within` the given sections, after which no two overlap
+///
+/// Once the order of the elves is fixed, shifting everyone is an L1 isotonic
+/// regression, solved exactly below. Finding the best order as well is NP-hard
+/// in general (it is earliness-tardiness scheduling), so small groups try every
+/// order, and larger ones sort by midpoint, which is exact for equal lengths.
+/// Otherwise the result is only as good as that order, and isn't marked exact.
+fn reassign(assignments: &[Region], within: Interval) -> Result<Reassignment, ReassignError> {
+    let intervals = assignments.iter()
+        .map(|r| r.interval().ok_or_else(|| ReassignError::NotOneDimensional(r.clone())))
+        .collect::<Result<Vec<Interval>, _>>()?;
+    let needed = intervals.iter().map(|i| i.length()).sum();
+    if needed > within.length() {
+        return Err(ReassignError::NoRoom { needed, available: within });
+    }
+
+    let mut order: Vec<usize> = (0..intervals.len()).collect();
+    if intervals.len() > EXHAUSTIVE_REASSIGN {
+        order.sort_by_key(|&k| intervals[k].start + intervals[k].end);
+        let exact = intervals.iter().all(|i| i.length() == intervals[0].length());
+        return Ok(Reassignment { exact, ..place(&intervals, &order, within) });
+    }
+    let mut best = place(&intervals, &order, within);
+    while next_permutation(&mut order) {
+        let placed = place(&intervals, &order, within);
+        if placed.change < best.change {
+            best = placed;
+        }
+    }
+    Ok(best)
+}
+
+/// Best placement of the assignments side by side in the given order
+fn place(intervals: &[Interval], order: &[usize], within: Interval) -> Reassignment {
+    // With p the length of everything placed before an assignment, its new start
+    // minus p must not decrease along the order: fit that to each start minus p.
+    let mut before = Vec::with_capacity(order.len());
+    let mut p = 0;
+    for &k in order {
+        before.push(p as i64);
+        p += intervals[k].length();
+    }
+    let targets: Vec<i64> = order.iter().zip(before.iter())
+        .map(|(&k, &p)| intervals[k].start as i64 - p)
+        .collect();
+
+    // slope trick: the heap holds the breakpoints of the best fit so far
+    let mut heap = BinaryHeap::new();
+    let mut tops = Vec::with_capacity(targets.len());
+    for &t in targets.iter() {
+        heap.push(t);
+        if heap.peek().copied().unwrap() > t {
+            heap.pop();
+            heap.push(t);
+        }
+        tops.push(*heap.peek().unwrap());
+    }
+    let mut fit = tops.clone();
+    for k in (0..fit.len().saturating_sub(1)).rev() {
+        fit[k] = fit[k].min(fit[k + 1]);
+    }
+
+    let (lo, hi) = (within.start as i64, (within.end + 1 - p) as i64);
+    let mut assignments = intervals.to_vec();
+    let mut change = 0;
+    for ((&k, &p), y) in order.iter().zip(before.iter()).zip(fit) {
+        let start = (y.clamp(lo, hi) + p) as usize;
+        change += 2 * start.abs_diff(intervals[k].start);
+        assignments[k] = Interval::new(start, start + intervals[k].length() - 1);
+    }
+    Reassignment { assignments, change, exact: true }
+}
+
+/// Steps to the next lexicographic permutation, false once back at the first
+fn next_permutation(order: &mut [usize]) -> bool {
+    let Some(i) = (1..order.len()).rev().find(|&i| order[i - 1] < order[i]) else {
+        order.reverse();
+        return false;
+    };
+    let j = (i..order.len()).rev().find(|&j| order[j] > order[i - 1]).unwrap();
+    order.swap(i - 1, j);
+    order[i..].reverse();
+    true
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Highlight {
+    /// Overlapping sections shown in red
+    Ansi,
+    /// Overlapping sections replaced by this character
+    Marker(char),
+}
+
+/// Draws assignments as strips of sections like the puzzle's `.234.....  2-4`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Timeline {
+    /// Sections per character
+    scale: usize,
+    highlight: Highlight,
+}
+
+impl Timeline {
+    /// Compresses the sections `within` into at most `width` characters
+    fn fitting(width: usize, within: Interval, highlight: Highlight) -> Timeline {
+        Timeline { scale: within.length().div_ceil(width.max(1)), highlight }
+    }
+
+    /// One strip per assignment, marking the sections others are also assigned to
+    fn render(&self, assignments: &[Interval], within: Interval) -> String {
+        // how many elves have each section, from the changes at every start and end
+        let mut elves = vec![0isize; within.length() + 1];
+        for a in assignments.iter().filter_map(|a| a.intersection(&within)) {
+            elves[a.start - within.start] += 1;
+            elves[a.end + 1 - within.start] -= 1;
+        }
+        for s in 1..elves.len() {
+            elves[s] += elves[s - 1];
+        }
+
+        let scale = self.scale.max(1);
+        let cells: Vec<Interval> = (within.start..=within.end).step_by(scale)
+            .map(|start| Interval::new(start, (start + scale - 1).min(within.end)))
+            .collect();
+        assignments.iter()
+            .map(|a| {
+                let strip: String = cells.iter()
+                    .map(|cell| match cell.intersection(a) {
+                        None => ".".to_string(),
+                        Some(both) => {
+                            let c = match scale {
+                                1 => char::from_digit((cell.start % 10) as u32, 10).unwrap(),
+                                _ => '#',
+                            };
+                            let shared = (both.start..=both.end).any(|s| elves[s - within.start] > 1);
+                            match (shared, self.highlight) {
+                                (false, _) => c.to_string(),
+                                (true, Highlight::Ansi) => format!("\x1b[31m{}\x1b[0m", c),
+                                (true, Highlight::Marker(m)) => m.to_string(),
+                            }
+                        },
+                    })
+                    .collect();
+                format!("{}  {}", strip, a)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Answers which elves cover a section, or touch a range of sections, in
+/// logarithmic time plus the number of elves found
+///
+/// Stabbing queries go through a centered interval tree; a range query is a
+/// stabbing query at its start plus every assignment starting inside it.
+struct SectionIndex {
+    root: Option<Box<Node>>,
+    by_start: Vec<(Interval, Elf)>,
+}
+
+struct Node {
+    center: usize,
+    /// Assignments containing the center, by start and by end (last first)
+    by_start: Vec<(Interval, Elf)>,
+    by_end: Vec<(Interval, Elf)>,
+    /// Assignments entirely before and after the center
+    left: Option<Box<Node>>,
+    right: Option<Box<Node>>,
+}
+
+impl Node {
+    fn build(assignments: Vec<(Interval, Elf)>) -> Option<Box<Node>> {
+        if assignments.is_empty() {
+            return None;
+        }
+        // the median endpoint, so at most half the assignments go either side
+        let mut ends: Vec<usize> = assignments.iter().flat_map(|(i, _)| [i.start, i.end]).collect();
+        let mid = ends.len() / 2;
+        let center = *ends.select_nth_unstable(mid).1;
+
+        let (mut left, mut right, mut here) = (Vec::new(), Vec::new(), Vec::new());
+        for a in assignments {
+            match a.0 {
+                i if i.end < center => left.push(a),
+                i if i.start > center => right.push(a),
+                _ => here.push(a),
+            }
+        }
+        let mut by_end = here.clone();
+        here.sort_by_key(|&(i, elf)| (i.start, elf));
+        by_end.sort_by_key(|&(i, elf)| (std::cmp::Reverse(i.end), elf));
+        Some(Box::new(Node {
+            center,
+            by_start: here,
+            by_end,
+            left: Node::build(left),
+            right: Node::build(right),
+        }))
+    }
+}
+
+impl SectionIndex {
+    fn new(assignments: &[(Elf, Interval)]) -> SectionIndex {
+        let mut by_start: Vec<(Interval, Elf)> = assignments.iter().map(|&(elf, i)| (i, elf)).collect();
+        by_start.sort_by_key(|&(i, elf)| (i.start, elf));
+        SectionIndex { root: Node::build(by_start.clone()), by_start }
+    }
+
+    fn stab(&self, section: usize, found: &mut Vec<Elf>) {
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            if section < n.center {
+                found.extend(n.by_start.iter().take_while(|(i, _)| i.start <= section).map(|&(_, e)| e));
+                node = n.left.as_deref();
+            } else if section > n.center {
+                found.extend(n.by_end.iter().take_while(|(i, _)| i.end >= section).map(|&(_, e)| e));
+                node = n.right.as_deref();
+            } else {
+                found.extend(n.by_start.iter().map(|&(_, e)| e));
+                break;
+            }
+        }
+    }
+
+    /// Elves whose assignment includes the section
+    fn covering(&self, section: usize) -> Vec<Elf> {
+        let mut found = Vec::new();
+        self.stab(section, &mut found);
+        found.sort();
+        found
+    }
+
+    /// Elves whose assignment shares at least one section with the range
+    fn touching(&self, range: Interval) -> Vec<Elf> {
+        let mut found = Vec::new();
+        self.stab(range.start, &mut found);
+        let from = self.by_start.partition_point(|(i, _)| i.start <= range.start);
+        let to = self.by_start.partition_point(|(i, _)| i.start <= range.end);
+        found.extend(self.by_start[from..to].iter().map(|&(_, e)| e));
+        found.sort();
+        found
+    }
+}
+
+/// The sections from the first to the last one assigned, along the first axis
+fn span(lines: &[Vec<Region>]) -> Option<Interval> {
+    let firsts = lines.iter().flatten().map(|r| r.0[0]);
+    let start = firsts.clone().map(|i| i.start).min()?;
+    let end = firsts.map(|i| i.end).max()?;
+    Some(Interval::new(start, end))
+}
+
 fn main() {
-    let contents: Vec<String> = include_str!("../input.txt").lines()
-        .map(|s| s.to_string()).collect();
-    // let contents: Vec<String> = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8".lines().map(|s| s.to_string()).collect();
-    let pairs: Vec<((usize,usize), (usize,usize))> = contents.iter()
-        .map(|s| {
-            let sections: Vec<usize> = s.split(",")
-                .flat_map(|p| p.split("-")
-                          .map(|sec| sec.parse::<usize>().unwrap() ))
-                .collect();
-            ((sections[0], sections[1]), (sections[2], sections[3]))
-        })
+    let lines = match parse(include_str!("../input.txt")) {
+        Ok(lines) => lines,
+        Err(e) => panic!("Cannot parse assignments: {}", e),
+    };
+    // let lines = parse("2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8");
+
+    // `day4 render [WIDTH]` draws each overlapping line, at most WIDTH (80)
+    // characters wide, with a reassignment that removes its overlaps
+    if std::env::args().nth(1).as_deref() == Some("render") {
+        let width = match std::env::args().nth(2).map(|s| s.parse()) {
+            Some(Ok(width)) => width,
+            Some(Err(e)) => panic!("Invalid timeline width: {}", e),
+            None => 80,
+        };
+        let Some(within) = span(&lines) else {
+            println!("No assignments to render");
+            return;
+        };
+        let highlight = match std::io::stdout().is_terminal() {
+            true => Highlight::Ansi,
+            false => Highlight::Marker('X'),
+        };
+        let timeline = Timeline::fitting(width, within, highlight);
+        for (k, line) in lines.iter().enumerate().filter(|&(_, line)| LineReport::new(line).has_overlap()) {
+            let sections: Option<Vec<Interval>> = line.iter().map(Region::interval).collect();
+            if let Some(sections) = sections {
+                println!("\n{}", timeline.render(&sections, within));
+            }
+            match reassign(line, within) {
+                Ok(plan) => println!("Line {} reassigned: {} ({})\n{}", k + 1,
+                    plan.assignments.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","),
+                    plan, timeline.render(&plan.assignments, within)),
+                Err(e) => println!("Line {} can't be reassigned: {}", k + 1, e),
+            }
+        }
+        match reassign(&lines.concat(), within) {
+            Ok(plan) => println!("Whole file reassigned with {}", plan),
+            Err(e) => println!("Whole file can't be reassigned: {}", e),
+        }
+        return;
+    }
+
+    // `day4 6 3-7` lists the elves covering section 6 and those touching 3-7
+    let queries: Vec<String> = std::env::args().skip(1).collect();
+    if !queries.is_empty() {
+        let sections: Vec<(Elf, Interval)> = elves(&lines).into_iter()
+            .filter_map(|(elf, r)| Some((elf, r.interval()?)))
+            .collect();
+        let index = SectionIndex::new(&sections);
+        for query in queries {
+            let found = match (query.parse::<usize>(), query.parse::<Interval>()) {
+                (Ok(section), _) => index.covering(section),
+                (_, Ok(range)) => index.touching(range),
+                (_, Err(e)) => {
+                    eprintln!("{}: {}", query, e);
+                    continue;
+                },
+            };
+            println!("{}: {} elves", query, found.len());
+            for elf in found {
+                println!("  {}", elf);
+            }
+        }
+        return;
+    }
+
+    let reports: Vec<LineReport> = lines.iter().map(|line| LineReport::new(line)).collect();
+    let full_overlaps = reports.iter().filter(|r| r.has_containment()).count();
+    let partial_overlaps = reports.iter().filter(|r| r.has_overlap()).count();
+
+    println!("Number of full overlaps: {}", full_overlaps);
+    println!("Number of partial overlaps: {}", partial_overlaps);
+
+    for (k, report) in reports.iter().enumerate().filter(|&(k, _)| lines[k].len() > 2) {
+        println!("Line {}: {}", k + 1, report);
+    }
+
+    let sections: Option<Vec<Interval>> = lines.concat().iter().map(Region::interval).collect();
+    match sections {
+        Some(assignments) => println!("{}", Coverage::from(assignments.as_slice())),
+        None => println!("Section coverage only applies to 1-D assignments"),
+    }
+
+    let across_lines: Vec<(Elf, Elf, Overlap)> = overlapping_pairs(&elves(&lines))
+        .into_iter()
+        .filter(|(a, b, _)| a.line != b.line)
         .collect();
-    let full_overlaps = pairs.iter()
-        .filter(|((a, b), (c, d))| (a >= c && b <= d) || (c >= a && d <= b))
-        .collect::<Vec<&((usize,usize), (usize,usize))>>();
-    let partial_overlaps = pairs.iter()
-        .filter(|&&((a, b), (c, d))| (a..b+1).contains(&c) || (c..d+1).contains(&a))
-        .collect::<Vec<&((usize,usize), (usize,usize))>>();
-
-    println!("Number of full overlaps: {}", full_overlaps.len());
-    println!("Number of partial overlaps: {}", partial_overlaps.len());
+    println!("Overlapping elves on different lines: {} ({} full)",
+        across_lines.len(),
+        across_lines.iter().filter(|(_, _, o)| *o == Overlap::Full).count());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use useful::Random;
+
+    const EXAMPLE: &str = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8";
+
+    fn example_assignments() -> Vec<Interval> {
+        parse(EXAMPLE).unwrap().concat()
+            .iter().map(|r| r.interval().unwrap()).collect()
+    }
+
+    #[test]
+    fn check_example_counts() {
+        let reports: Vec<LineReport> = parse(EXAMPLE).unwrap()
+            .iter().map(|line| LineReport::new(line)).collect();
+        assert_eq!(2, reports.iter().filter(|r| r.has_containment()).count());
+        assert_eq!(4, reports.iter().filter(|r| r.has_overlap()).count());
+    }
+
+    #[test]
+    fn check_n_way_line_report() {
+        let lines = parse("1-9,2-3,3-5,7-8,10-10\n1-2,3-4,5-6").unwrap();
+        assert_eq!(LineReport {
+            containments: vec![(0, 1), (0, 2), (0, 3)],
+            overlaps: vec![(0, 1), (0, 2), (0, 3), (1, 2)],
+        }, LineReport::new(&lines[0]));
+        assert_eq!("elf 1 contains elf 2; elf 1 contains elf 3; elf 1 contains elf 4; \
+                    elf 1 overlaps elf 2; elf 1 overlaps elf 3; elf 1 overlaps elf 4; elf 2 overlaps elf 3",
+                   LineReport::new(&lines[0]).to_string());
+        assert_eq!(LineReport::default(), LineReport::new(&lines[1]));
+        assert_eq!("no overlaps", LineReport::new(&lines[1]).to_string());
+    }
+
+    #[test]
+    fn check_box_assignments() {
+        let lines = parse("2-4x6-8,3-3x5-9\n1-2x1-2,1-2x3-4").unwrap();
+        assert_eq!(Region(vec![Interval::new(2, 4), Interval::new(6, 8)]), lines[0][0]);
+        assert_eq!("2-4x6-8", lines[0][0].to_string());
+        assert_eq!(LineReport { containments: vec![], overlaps: vec![(0, 1)] },
+                   LineReport::new(&lines[0]));
+        // the first axes overlap, but the boxes don't
+        assert_eq!(LineReport::default(), LineReport::new(&lines[1]));
+        let cubes = parse("1-5x1-5x1-5,2-3x2-3x2-3,5-6x5-6x5-6").unwrap();
+        assert_eq!(LineReport { containments: vec![(0, 1)], overlaps: vec![(0, 1), (0, 2)] },
+                   LineReport::new(&cubes[0]));
+    }
+
+    #[test]
+    fn check_mixed_dimensions() {
+        assert_eq!(Err(ParseError::MixedDimensions {
+            line: 2,
+            region: Region(vec![Interval::new(2, 3), Interval::new(2, 3)]),
+            expected: 1,
+        }), parse("1-5,3-4\n1-5,2-3x2-3"));
+        assert_eq!("Line 2: 2-D assignment 2-3x2-3 among 1-D ones",
+                   parse("1-5,3-4\n1-5,2-3x2-3").unwrap_err().to_string());
+        assert!(matches!(parse("1-5\n3-x"),
+                         Err(ParseError::Interval { line: 2, error: ParseIntervalError::Bound(_) })));
+    }
+
+    // cheapest disjoint placement by trying every start for every assignment
+    fn brute_force_reassign(intervals: &[Interval], within: Interval) -> Option<usize> {
+        fn go(rest: &[Interval], placed: &mut Vec<Interval>, within: Interval, change: usize) -> Option<usize> {
+            let Some((i, rest)) = rest.split_first() else { return Some(change) };
+            let mut best = None;
+            for start in within.start..=within.end + 1 - i.length() {
+                let n = Interval::new(start, start + i.length() - 1);
+                if placed.iter().any(|p| p.overlaps(&n)) {
+                    continue;
+                }
+                placed.push(n);
+                let found = go(rest, placed, within, change + 2 * start.abs_diff(i.start));
+                placed.pop();
+                best = best.into_iter().chain(found).min();
+            }
+            best
+        }
+        go(intervals, &mut Vec::new(), within, 0)
+    }
+
+    #[test]
+    fn check_reassign_against_brute_force() {
+        let within = Interval::new(1, 9);
+        let intervals: Vec<Interval> = (1..=6).flat_map(|a| (a..=6).map(move |b| Interval::new(a, b))).collect();
+        let mut groups: Vec<Vec<Interval>> = Vec::new();
+        for &a in intervals.iter() {
+            for &b in intervals.iter() {
+                groups.push(vec![a, b]);
+            }
+        }
+        groups.extend(["2-4,3-3,1-2", "1-5,2-2,4-4", "5-6,1-9,5-5", "3-4,3-4,3-4,2-2"].iter()
+            .map(|s| parse(s).unwrap()[0].iter().map(|r| r.interval().unwrap()).collect()));
+
+        for group in groups {
+            let regions: Vec<Region> = group.iter().map(|&i| Region(vec![i])).collect();
+            match (brute_force_reassign(&group, within), reassign(&regions, within)) {
+                (Some(change), Ok(plan)) => {
+                    assert_eq!(change, plan.change, "{:?} -> {:?}", group, plan);
+                    for (k, (a, b)) in plan.assignments.iter().zip(group.iter()).enumerate() {
+                        assert_eq!(a.length(), b.length());
+                        assert!(within.covers(a));
+                        assert!(plan.assignments[k + 1..].iter().all(|c| !a.overlaps(c)), "{:?}", plan);
+                    }
+                },
+                (None, Err(ReassignError::NoRoom { .. })) => (),
+                (expected, got) => panic!("{:?}: expected {:?}, got {:?}", group, expected, got),
+            }
+        }
+    }
+
+    #[test]
+    fn check_reassign_example_lines() {
+        let lines = parse(EXAMPLE).unwrap();
+        let within = span(&lines).unwrap();
+        assert_eq!(Interval::new(2, 9), within);
+        assert_eq!(Ok(Reassignment { assignments: lines[0].iter().map(|r| r.interval().unwrap()).collect(), change: 0, exact: true }),
+                   reassign(&lines[0], within));
+        assert_eq!(Ok(Reassignment { assignments: vec![Interval::new(4, 6), Interval::new(7, 9)], change: 2, exact: true }),
+                   reassign(&lines[2], within));
+        assert_eq!(Err(ReassignError::NoRoom { needed: 12, available: within }),
+                   reassign(&lines[3], within));
+        assert_eq!(Err(ReassignError::NoRoom { needed: 42, available: within }),
+                   reassign(&lines.concat(), within));
+        assert_eq!(Err(ReassignError::NotOneDimensional(Region(vec![Interval::new(1, 2); 2]))),
+                   reassign(&parse("1-2x1-2").unwrap()[0], within));
+    }
+
+    #[test]
+    fn check_reassign_in_midpoint_order() {
+        // too many to try every order, so 3-3 goes before 3-4, and all of them
+        // would rather move left than the lowest section allows
+        let line = &parse("1-2,1-2,2-3,3-4,3-3,5-6,6-7,9-9").unwrap()[0];
+        let plan = reassign(line, Interval::new(0, 20)).unwrap();
+        assert_eq!(vec![(0, 1), (2, 3), (4, 5), (7, 8), (6, 6), (9, 10), (11, 12), (13, 13)],
+                   plan.assignments.iter().map(|i| (i.start, i.end)).collect::<Vec<_>>());
+        assert_eq!(48, plan.change);
+        assert!(!plan.exact);
+        assert_eq!("a total change of 48, which may not be the smallest", plan.to_string());
+
+        // the midpoint order is the best one when all lengths are the same
+        let line = &parse("1-2,1-2,2-3,3-4,4-5,5-6,6-7").unwrap()[0];
+        let plan = reassign(line, Interval::new(1, 16)).unwrap();
+        assert!(plan.exact);
+        let intervals: Vec<Interval> = line.iter().map(|r| r.interval().unwrap()).collect();
+        assert_eq!(brute_force_reassign(&intervals, Interval::new(1, 16)), Some(plan.change));
+        assert_eq!(format!("the smallest total change, {}", plan.change), plan.to_string());
+    }
+
+    fn line_intervals(s: &str) -> Vec<Interval> {
+        s.split(',').map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn check_example_timeline() {
+        let timeline = Timeline { scale: 1, highlight: Highlight::Marker('X') };
+        let within = Interval::new(1, 9);
+        assert_eq!(".234.....  2-4\n.....678.  6-8",
+                   timeline.render(&line_intervals("2-4,6-8"), within));
+        assert_eq!("....56X..  5-7\n......X89  7-9",
+                   timeline.render(&line_intervals("5-7,7-9"), within));
+        assert_eq!(".2XXXXX8.  2-8\n..XXXXX..  3-7",
+                   timeline.render(&line_intervals("2-8,3-7"), within));
+    }
+
+    #[test]
+    fn check_scaled_timeline() {
+        let within = Interval::new(1, 95);
+        let timeline = Timeline::fitting(10, within, Highlight::Ansi);
+        assert_eq!(10, timeline.scale);
+        assert_eq!("##\x1b[31m#\x1b[0m.......  1-25\n..\x1b[31m#\x1b[0m##.....  25-45\n.........#  95-95",
+                   timeline.render(&line_intervals("1-25,25-45,95-95"), within));
+    }
+
+    // a fixed pseudo-random file of assignments within 1..=max
+    fn random_sections(n: usize, max: usize, seed: u64) -> Vec<(Elf, Interval)> {
+        let mut random = Random::new(seed);
+        let mut next = || random.below(max) + 1;
+        (0..n)
+            .map(|k| {
+                let (a, b) = (next(), next());
+                (Elf { line: k / 2, slot: k % 2 }, Interval::new(a.min(b), a.max(b)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_section_index_against_scan() {
+        for (n, seed) in [(0, 1), (1, 2), (2, 3), (7, 4), (40, 5), (200, 6)] {
+            let sections = random_sections(n, 30, seed);
+            let index = SectionIndex::new(&sections);
+            for s in 0..=31 {
+                let scan: Vec<Elf> = sections.iter()
+                    .filter(|(_, i)| i.contains(s)).map(|&(e, _)| e).collect();
+                assert_eq!(scan, index.covering(s), "n {} seed {} section {}", n, seed, s);
+                for t in s..=31 {
+                    let range = Interval::new(s, t);
+                    let scan: Vec<Elf> = sections.iter()
+                        .filter(|(_, i)| i.overlaps(&range)).map(|&(e, _)| e).collect();
+                    assert_eq!(scan, index.touching(range), "n {} seed {} range {}", n, seed, range);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_example_section_index() {
+        let sections: Vec<(Elf, Interval)> = elves(&parse(EXAMPLE).unwrap())
+            .into_iter().map(|(e, r)| (e, r.interval().unwrap())).collect();
+        let index = SectionIndex::new(&sections);
+        let elf = |line, slot| Elf { line, slot };
+        assert_eq!(vec![elf(0, 0), elf(1, 0), elf(3, 0), elf(5, 0)], index.covering(2));
+        assert_eq!(Vec::<Elf>::new(), index.covering(1));
+        assert_eq!(vec![elf(0, 1), elf(2, 1), elf(3, 0), elf(5, 1)], index.touching(Interval::new(8, 12)));
+    }
+
+    #[test]
+    fn check_overlapping_pairs_against_all_pairs() {
+        let elves = elves(&parse(&format!("{}\n9-12,1-1", EXAMPLE)).unwrap());
+        let mut expected = Vec::new();
+        for (i, (a, x)) in elves.iter().enumerate() {
+            for (b, y) in elves[i + 1..].iter() {
+                if x.covers(y) || y.covers(x) {
+                    expected.push((*a, *b, Overlap::Full));
+                } else if x.overlaps(y) {
+                    expected.push((*a, *b, Overlap::Partial));
+                }
+            }
+        }
+        assert_eq!(expected, overlapping_pairs(&elves));
+    }
+
+    #[test]
+    fn check_overlapping_pairs_on_example_lines() {
+        // the same overlaps as part 1 and 2 when restricted to a single line
+        let same_line: Vec<(usize, Overlap)> = overlapping_pairs(&elves(&parse(EXAMPLE).unwrap()))
+            .into_iter()
+            .filter(|(a, b, _)| a.line == b.line)
+            .map(|(a, _, overlap)| (a.line, overlap))
+            .collect();
+        assert_eq!(vec![(2, Overlap::Partial), (3, Overlap::Full), (4, Overlap::Full), (5, Overlap::Partial)],
+                   same_line);
+    }
+
+    #[test]
+    fn check_example_coverage() {
+        assert_eq!(Coverage {
+            covered: 8,
+            gaps: vec![],
+            max_elves: 8,
+            busiest: vec![Interval::new(6, 6)],
+        }, Coverage::from(example_assignments().as_slice()));
+    }
+
+    #[test]
+    fn check_coverage_gaps() {
+        let assignments: Vec<Interval> = ["1-3", "5-6", "2-3", "9-9", "5-5", "6-6"].iter()
+            .map(|s| s.parse().unwrap()).collect();
+        assert_eq!(Coverage {
+            covered: 6,
+            gaps: vec![Interval::new(4, 4), Interval::new(7, 8)],
+            max_elves: 2,
+            busiest: vec![Interval::new(2, 3), Interval::new(5, 6)],
+        }, Coverage::from(assignments.as_slice()));
+        assert_eq!(0, Coverage::from(&[][..]).covered);
+    }
 }
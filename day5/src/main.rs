@@ -1,169 +1,1424 @@
-fn transpose<T: Clone + std::fmt::Debug>(matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let size = {
-        let cols = matrix.len();
-        let rows: Vec<usize> = matrix.iter().map(|r| r.len()).collect();
-        if rows.is_empty()
-            || !rows
-                .iter()
-                .fold((true, rows[0]), |prev, len| {
-                    (prev.1 == *len && prev.0, *len)
-                })
-                .0
-        {
-            panic!("Not all row lengths are equal! Rows: {:?}", rows);
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::IsTerminal;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// A crate's label, written between brackets like "[A]" or "[AB]"
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Crate(Rc<str>);
+
+impl Crate {
+    /// Labels are non-empty, without spaces or brackets
+    fn new(label: &str) -> Option<Crate> {
+        let valid = !label.is_empty()
+            && !label
+                .chars()
+                .any(|c| c.is_whitespace() || c == '[' || c == ']');
+        valid.then(|| Crate(label.into()))
+    }
+}
+
+impl fmt::Display for Crate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Stacks(Vec<Vec<Crate>>);
+
+/// What's wrong with a stack diagram, by 1-based line and column
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum DiagramError {
+    /// There is no numbered footer line
+    NoFooter,
+    /// The footer doesn't number the stacks 1, 2, 3, ...
+    BadLabel { column: usize, label: String },
+    /// A crate isn't directly above exactly one stack number
+    Misaligned { line: usize, column: usize },
+    /// A '[' without its ']'
+    Unclosed { line: usize, column: usize },
+    /// Crate labels are non-empty, without spaces or brackets
+    BadCrate {
+        line: usize,
+        column: usize,
+        label: String,
+    },
+    /// Something other than a crate or a space
+    Unexpected {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    /// A crate with nothing below it in its stack
+    Floating { line: usize, stack: usize },
+}
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagramError::NoFooter => write!(f, "Stack diagram has no numbered footer"),
+            DiagramError::BadLabel { column, label } => {
+                write!(
+                    f,
+                    "Footer column {}: expected the next stack number, found {:?}",
+                    column, label
+                )
+            }
+            DiagramError::Misaligned { line, column } => {
+                write!(
+                    f,
+                    "Line {}, column {}: crate isn't above a stack number",
+                    line, column
+                )
+            }
+            DiagramError::Unclosed { line, column } => {
+                write!(f, "Line {}, column {}: '[' is never closed", line, column)
+            }
+            DiagramError::BadCrate {
+                line,
+                column,
+                label,
+            } => {
+                write!(
+                    f,
+                    "Line {}, column {}: invalid crate [{}]",
+                    line, column, label
+                )
+            }
+            DiagramError::Unexpected {
+                line,
+                column,
+                found,
+            } => {
+                write!(
+                    f,
+                    "Line {}, column {}: unexpected {:?}",
+                    line, column, found
+                )
+            }
+            DiagramError::Floating { line, stack } => {
+                write!(
+                    f,
+                    "Line {}: crate in stack {} has nothing below it",
+                    line, stack
+                )
+            }
         }
-        let rows: usize = rows[0];
-        (cols, rows)
-    };
+    }
+}
+
+impl std::error::Error for DiagramError {}
 
-    let mut out_matrix = Vec::new();
-    for i in 0..size.1 {
-        out_matrix.push(Vec::new()); // create new stack for every element of row
-        for j in 0..size.0 {
-            out_matrix[i].push(matrix[size.0 - j - 1][i].clone());
+/// Runs of non-space characters in a line, as (first column, last column, text)
+fn tokens(line: &str) -> Vec<(usize, usize, String)> {
+    let mut tokens: Vec<(usize, usize, String)> = Vec::new();
+    let mut prev_space = true;
+    for (i, c) in line.chars().enumerate() {
+        match (c.is_whitespace(), prev_space) {
+            (true, _) => (),
+            (false, true) => tokens.push((i, i, c.to_string())),
+            (false, false) => {
+                let last = tokens.last_mut().unwrap();
+                last.1 = i;
+                last.2.push(c);
+            }
         }
-        // println!("Row: {:?}", out_matrix[i]);
+        prev_space = c.is_whitespace();
     }
-    out_matrix
+    tokens
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Stacks(Vec<Vec<u8>>);
+impl FromStr for Stacks {
+    type Err = DiagramError;
+
+    /// Finds the stacks' columns from the numbered footer, so rows may be
+    /// ragged (trailing spaces stripped) and there may be any number of stacks
+    fn from_str(diagram: &str) -> Result<Stacks, DiagramError> {
+        let lines: Vec<&str> = diagram.lines().collect();
+        let end = lines
+            .iter()
+            .rposition(|l| !l.trim().is_empty())
+            .ok_or(DiagramError::NoFooter)?;
+        let (footer, rows) = (lines[end], &lines[..end]);
+        let labels = tokens(footer);
+        for (k, (column, _, label)) in labels.iter().enumerate() {
+            if label.parse::<usize>() != Ok(k + 1) {
+                return Err(DiagramError::BadLabel {
+                    column: column + 1,
+                    label: label.clone(),
+                });
+            }
+        }
+        if labels.is_empty() {
+            return Err(DiagramError::NoFooter);
+        }
+
+        // rows of crates from the top down, one slot per stack
+        let mut grid: Vec<Vec<Option<Crate>>> = Vec::new();
+        for (l, row) in rows.iter().enumerate() {
+            let (line, chars) = (l + 1, row.chars().collect::<Vec<char>>());
+            let mut slots = vec![None; labels.len()];
+            let mut i = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    c if c.is_whitespace() => i += 1,
+                    '[' => {
+                        let close = (i + 1..chars.len()).find(|&j| chars[j] == ']').ok_or(
+                            DiagramError::Unclosed {
+                                line,
+                                column: i + 1,
+                            },
+                        )?;
+                        // narrower labels are padded to the column's width
+                        let label: String = chars[i + 1..close].iter().collect();
+                        let label = label.trim().to_string();
+                        let Some(c) = Crate::new(&label) else {
+                            return Err(DiagramError::BadCrate {
+                                line,
+                                column: i + 1,
+                                label,
+                            });
+                        };
+                        let stacks: Vec<usize> = labels
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, &(first, last, _))| first < close && last > i)
+                            .map(|(k, _)| k)
+                            .collect();
+                        let &[k] = stacks.as_slice() else {
+                            return Err(DiagramError::Misaligned {
+                                line,
+                                column: i + 1,
+                            });
+                        };
+                        if slots[k].is_some() {
+                            return Err(DiagramError::Misaligned {
+                                line,
+                                column: i + 1,
+                            });
+                        }
+                        slots[k] = Some(c);
+                        i = close + 1;
+                    }
+                    found => {
+                        return Err(DiagramError::Unexpected {
+                            line,
+                            column: i + 1,
+                            found,
+                        })
+                    }
+                }
+            }
+            grid.push(slots);
+        }
+
+        let mut stacks = vec![Vec::new(); labels.len()];
+        for (l, slots) in grid.iter().enumerate().rev() {
+            for (k, slot) in slots.iter().enumerate() {
+                match (slot, stacks[k].len() + l + 1 == rows.len()) {
+                    (Some(c), true) => stacks[k].push(c.clone()),
+                    (Some(_), false) => {
+                        return Err(DiagramError::Floating {
+                            line: l + 1,
+                            stack: k + 1,
+                        })
+                    }
+                    (None, _) => (),
+                }
+            }
+        }
+        Ok(Stacks(stacks))
+    }
+}
+
+impl fmt::Display for Stacks {
+    /// The puzzle's diagram, crates above the numbered footer, with every
+    /// column as wide as the widest label
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let height = self.0.iter().map(|s| s.len()).max().unwrap_or(0);
+        let width = self
+            .0
+            .iter()
+            .flatten()
+            .map(|c| c.0.chars().count())
+            .max()
+            .unwrap_or(1);
+        for row in (0..height).rev() {
+            let cells: Vec<String> = self
+                .0
+                .iter()
+                .map(|stack| match stack.get(row) {
+                    Some(c) => format!("[{:^width$}]", c),
+                    None => " ".repeat(width + 2),
+                })
+                .collect();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        let labels: Vec<String> = (1..=self.0.len())
+            .map(|k| format!("{:^w$}", k, w = width + 2))
+            .collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
 
 impl Stacks {
+    /// Carries out the instructions one after the other with the given crane,
+    /// as far as each one can be
+    #[cfg(test)]
+    fn run<C: Crane + ?Sized>(mut self, crane: &mut C, instructs: &[Instruction]) -> Self {
+        for &instruction in instructs {
+            self.apply(crane, instruction);
+        }
+        self
+    }
+
+    /// The source and target stacks of a move, unless they are the same one
+    fn pair(&mut self, from: usize, to: usize) -> Option<(&mut Vec<Crate>, &mut Vec<Crate>)> {
+        match from.cmp(&to) {
+            Ordering::Less => {
+                let (below, above) = self.0.split_at_mut(to);
+                Some((&mut below[from], &mut above[0]))
+            }
+            Ordering::Greater => {
+                let (below, above) = self.0.split_at_mut(from);
+                Some((&mut above[0], &mut below[to]))
+            }
+            Ordering::Equal => None,
+        }
+    }
+
+    /// Moves crates straight from one stack to the other with `drain` and
+    /// `extend`, so a move costs the same per crate however many there are
+    fn transfer(&mut self, transfer: Transfer) {
+        if let Some((from, to)) = self.pair(transfer.from, transfer.to) {
+            let at = from.len().saturating_sub(transfer.count);
+            match transfer.reversed {
+                true => to.extend(from.drain(at..).rev()),
+                false => to.extend(from.drain(at..)),
+            }
+        }
+    }
+
+    #[cfg(test)]
     fn execute_2(self, instructs: Vec<Instruction>) -> Self {
-        instructs
-            .into_iter()
-            .fold(self, |stacks, instruction| instruction.execute_2(stacks))
+        self.run(&mut CrateMover9001, &instructs)
     }
 
+    #[cfg(test)]
     fn execute(self, instructs: Vec<Instruction>) -> Self {
-        instructs
-            .into_iter()
-            .fold(self, |stacks, instruction| instruction.execute(stacks))
+        self.run(&mut CrateMover9000, &instructs)
     }
 
     fn message(&self) -> String {
+        self.message_with("")
+    }
+
+    /// The labels of the top crates, joined by the separator; empty stacks have none
+    fn message_with(&self, separator: &str) -> String {
         self.0
             .iter()
-            .map(|v| v.last().copied().unwrap() as char)
+            .filter_map(|v| v.last())
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
+
+/// One lift of `count` crates off the top of a stack onto another, either
+/// keeping their order or turned upside down
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Transfer {
+    from: usize,
+    to: usize,
+    count: usize,
+    reversed: bool,
+}
+
+impl Transfer {
+    /// The transfer putting the crates back where they were
+    fn inverse(self) -> Transfer {
+        Transfer {
+            from: self.to,
+            to: self.from,
+            ..self
+        }
+    }
+}
+
+/// A crane model, deciding how the crates of one instruction get moved
+trait Crane {
+    fn name(&self) -> String;
+
+    /// The lifts that carry out an instruction the stacks can satisfy
+    fn transfers(&mut self, instruction: Instruction) -> Vec<Transfer>;
+
+    /// What the crane remembers of earlier lifts, for cranes whose next lift
+    /// depends on them; the planner tells states apart by it
+    fn phase(&self) -> usize {
+        0
+    }
+}
+
+fn single(instruction: Instruction, reversed: bool) -> Vec<Transfer> {
+    vec![Transfer {
+        from: instruction.from,
+        to: instruction.to,
+        count: instruction.amount,
+        reversed,
+    }]
+}
+
+/// Moves one crate at a time, so the moved crates end up reversed (part 1)
+#[derive(Clone)]
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn transfers(&mut self, instruction: Instruction) -> Vec<Transfer> {
+        single(instruction, true)
+    }
+}
+
+/// Moves all crates at once, keeping their order (part 2)
+#[derive(Clone)]
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn transfers(&mut self, instruction: Instruction) -> Vec<Transfer> {
+        single(instruction, false)
+    }
+}
+
+/// A CrateMover 9001 that can't lift more than `capacity` crates, so bigger
+/// moves are made one crate at a time
+#[derive(Clone)]
+struct LimitedLift {
+    capacity: usize,
+}
+
+impl Crane for LimitedLift {
+    fn name(&self) -> String {
+        format!("CrateMover 9001 lifting at most {}", self.capacity)
+    }
+
+    fn transfers(&mut self, instruction: Instruction) -> Vec<Transfer> {
+        single(instruction, instruction.amount > self.capacity)
+    }
+}
+
+/// Moves the crates `size` at a time from the top, keeping the order within each chunk
+#[derive(Clone)]
+struct Chunked {
+    size: usize,
+}
+
+impl Crane for Chunked {
+    fn name(&self) -> String {
+        format!("CrateMover 9001 in chunks of {}", self.size)
+    }
+
+    fn transfers(&mut self, instruction: Instruction) -> Vec<Transfer> {
+        let size = self.size.max(1);
+        let mut transfers = Vec::new();
+        let mut left = instruction.amount;
+        while left > 0 {
+            let amount = left.min(size);
+            transfers.extend(single(
+                Instruction {
+                    amount,
+                    ..instruction
+                },
+                false,
+            ));
+            left -= amount;
+        }
+        transfers
+    }
+}
+
+/// Lifts everything at once, but turns every other load upside down
+#[derive(Clone, Default)]
+struct Alternating {
+    lifts: usize,
+}
+
+impl Crane for Alternating {
+    fn name(&self) -> String {
+        "CrateMover 9001 reversing every other lift".to_string()
+    }
+
+    fn transfers(&mut self, instruction: Instruction) -> Vec<Transfer> {
+        self.lifts += 1;
+        single(instruction, self.lifts.is_multiple_of(2))
+    }
+
+    fn phase(&self) -> usize {
+        self.lifts % 2
+    }
+}
+
+/// A run of instructions that can be stepped through both ways
+///
+/// Only the current stacks are kept, along with the transfers each instruction
+/// made, so going back applies the inverse transfers in reverse order.
+struct History {
+    stacks: Stacks,
+    instructions: Vec<Instruction>,
+    transfers: Vec<Transfer>,
+    /// Instruction k made `transfers[steps[k]..steps[k + 1]]`
+    steps: Vec<usize>,
+    /// How many instructions the current stacks have had applied
+    position: usize,
+}
+
+impl History {
+    /// Runs all instructions leniently to record them, ending up after the last one
+    fn record<C: Crane + ?Sized>(
+        mut stacks: Stacks,
+        crane: &mut C,
+        instructs: &[Instruction],
+    ) -> History {
+        let mut transfers = Vec::new();
+        let mut steps = vec![0];
+        for &instruction in instructs {
+            transfers.extend(stacks.apply(crane, instruction));
+            steps.push(transfers.len());
+        }
+        History {
+            stacks,
+            instructions: instructs.to_vec(),
+            transfers,
+            steps,
+            position: instructs.len(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// The instruction that was applied last, if any
+    fn last(&self) -> Option<Instruction> {
+        self.position.checked_sub(1).map(|k| self.instructions[k])
+    }
+
+    /// The transfers undoing instruction k, in the order to make them
+    fn inverse(&self, k: usize) -> Vec<Transfer> {
+        self.transfers[self.steps[k]..self.steps[k + 1]]
+            .iter()
+            .rev()
+            .map(|t| t.inverse())
             .collect()
     }
+
+    /// Undoes the last instruction applied, false if at the start already
+    fn back(&mut self) -> bool {
+        let Some(k) = self.position.checked_sub(1) else {
+            return false;
+        };
+        for transfer in self.inverse(k) {
+            self.stacks.transfer(transfer);
+        }
+        self.position = k;
+        true
+    }
+
+    /// Applies the next instruction again, false if at the end already
+    fn forward(&mut self) -> bool {
+        let k = self.position;
+        if k == self.len() {
+            return false;
+        }
+        for &transfer in &self.transfers[self.steps[k]..self.steps[k + 1]] {
+            self.stacks.transfer(transfer);
+        }
+        self.position += 1;
+        true
+    }
+
+    /// Moves to the stacks after the first n instructions
+    fn jump(&mut self, n: usize) {
+        let n = n.min(self.len());
+        while self.position > n {
+            self.back();
+        }
+        while self.position < n {
+            self.forward();
+        }
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Instruction {
     from: usize,
     to: usize,
     amount: usize,
 }
 
-impl From<&str> for Instruction {
-    fn from(s: &str) -> Instruction {
-        let bits: Vec<&str> = s.split(" ").collect();
-        let get_bit = |i: usize| {
-            bits[i]
-                .parse::<usize>()
-                .expect(format!("Cannot parse {i}th word ({})", bits[i]).as_str())
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.amount,
+            self.from + 1,
+            self.to + 1
+        )
+    }
+}
+
+/// One step of the instruction language, a line like "swap 1 3"
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Operation {
+    Move(Instruction),
+    /// Exchange two whole stacks
+    Swap(usize, usize),
+    /// Turn a stack upside down
+    Reverse(usize),
+    /// Shift every stack one place over, the end one going round to the other end
+    Rotate(Direction),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Direction {
+    Left,
+    Right,
+}
+
+impl Operation {
+    fn as_move(&self) -> Option<Instruction> {
+        match self {
+            Operation::Move(instruction) => Some(*instruction),
+            _ => None,
+        }
+    }
+}
+
+impl From<Instruction> for Operation {
+    fn from(instruction: Instruction) -> Operation {
+        Operation::Move(instruction)
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Move(instruction) => write!(f, "{}", instruction),
+            Operation::Swap(a, b) => write!(f, "swap {} {}", a + 1, b + 1),
+            Operation::Reverse(a) => write!(f, "reverse {}", a + 1),
+            Operation::Rotate(Direction::Left) => write!(f, "rotate left"),
+            Operation::Rotate(Direction::Right) => write!(f, "rotate right"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ParseInstructionError {
+    /// A line starting with a word that isn't an operation
+    UnknownOperation(String),
+    /// Some other word, or the end of the line, where the grammar needs this
+    Expected {
+        expected: &'static str,
+        found: Option<String>,
+    },
+    Number(String),
+    /// Stacks are numbered from 1
+    StackZero(String),
+    /// More words after a complete operation
+    Trailing(String),
+    /// An operation where only a move will do
+    NotAMove(Operation),
+}
+
+impl fmt::Display for ParseInstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseInstructionError::UnknownOperation(w) => {
+                write!(
+                    f,
+                    "Unknown operation {:?}, expected move, swap, reverse or rotate",
+                    w
+                )
+            }
+            ParseInstructionError::Expected {
+                expected,
+                found: Some(w),
+            } => write!(f, "Expected {}, found {:?}", expected, w),
+            ParseInstructionError::Expected {
+                expected,
+                found: None,
+            } => write!(f, "Expected {} before the end of the line", expected),
+            ParseInstructionError::Number(w) => write!(f, "Cannot parse number {:?}", w),
+            ParseInstructionError::StackZero(s) => {
+                write!(f, "Stacks are numbered from 1, in {:?}", s)
+            }
+            ParseInstructionError::Trailing(w) => {
+                write!(f, "Unexpected {:?} after the operation", w)
+            }
+            ParseInstructionError::NotAMove(op) => {
+                write!(f, "Expected a move, found {:?}", op.to_string())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseInstructionError {}
+
+/// The words of one line, with any '#' comment left out
+struct Words<'a> {
+    line: &'a str,
+    words: std::str::SplitWhitespace<'a>,
+}
+
+impl<'a> Words<'a> {
+    fn new(line: &'a str) -> Words<'a> {
+        let code = line.split('#').next().unwrap_or_default();
+        Words {
+            line,
+            words: code.split_whitespace(),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    fn keyword(&mut self, expected: &'static str) -> Result<(), ParseInstructionError> {
+        match self.next() {
+            Some(w) if w == expected => Ok(()),
+            found => Err(ParseInstructionError::Expected {
+                expected,
+                found: found.map(String::from),
+            }),
+        }
+    }
+
+    fn number(&mut self, expected: &'static str) -> Result<usize, ParseInstructionError> {
+        let w = self.next().ok_or(ParseInstructionError::Expected {
+            expected,
+            found: None,
+        })?;
+        w.parse()
+            .map_err(|_| ParseInstructionError::Number(w.to_string()))
+    }
+
+    /// A 1-based stack number, as a 0-based index
+    fn stack(&mut self) -> Result<usize, ParseInstructionError> {
+        self.number("a stack number")?
+            .checked_sub(1)
+            .ok_or_else(|| ParseInstructionError::StackZero(self.line.to_string()))
+    }
+
+    fn end(&mut self) -> Result<(), ParseInstructionError> {
+        match self.next() {
+            Some(w) => Err(ParseInstructionError::Trailing(w.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Operation {
+    type Err = ParseInstructionError;
+
+    /// Parses one of
+    ///
+    /// ```text
+    /// move N from A to B
+    /// swap A B
+    /// reverse A
+    /// rotate left|right
+    /// ```
+    ///
+    /// optionally followed by a '#' comment.
+    fn from_str(s: &str) -> Result<Operation, ParseInstructionError> {
+        let mut words = Words::new(s);
+        let operation = match words.next() {
+            Some("move") => {
+                let amount = words.number("a number of crates")?;
+                words.keyword("from")?;
+                let from = words.stack()?;
+                words.keyword("to")?;
+                let to = words.stack()?;
+                Operation::Move(Instruction { from, to, amount })
+            }
+            Some("swap") => Operation::Swap(words.stack()?, words.stack()?),
+            Some("reverse") => Operation::Reverse(words.stack()?),
+            Some("rotate") => match words.next() {
+                Some("left") => Operation::Rotate(Direction::Left),
+                Some("right") => Operation::Rotate(Direction::Right),
+                found => {
+                    return Err(ParseInstructionError::Expected {
+                        expected: "left or right",
+                        found: found.map(String::from),
+                    })
+                }
+            },
+            Some(w) => return Err(ParseInstructionError::UnknownOperation(w.to_string())),
+            None => {
+                return Err(ParseInstructionError::Expected {
+                    expected: "an operation",
+                    found: None,
+                })
+            }
+        };
+        words.end()?;
+        Ok(operation)
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseInstructionError;
+
+    fn from_str(s: &str) -> Result<Instruction, ParseInstructionError> {
+        let operation: Operation = s.parse()?;
+        operation
+            .as_move()
+            .ok_or(ParseInstructionError::NotAMove(operation))
+    }
+}
+
+impl From<&str> for Instruction {
+    fn from(s: &str) -> Instruction {
+        s.parse().unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+/// A line of a program that doesn't parse
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProgramError {
+    line: usize,
+    error: ParseInstructionError,
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+/// The operations of a program, one per line, each with the number of its
+/// line, counting the first as `first_line`; blank and comment lines are skipped
+fn parse_program(text: &str, first_line: usize) -> Result<Vec<(usize, Operation)>, ProgramError> {
+    (first_line..)
+        .zip(text.lines())
+        .filter(|(_, line)| Words::new(line).next().is_some())
+        .map(|(line, text)| match text.parse() {
+            Ok(operation) => Ok((line, operation)),
+            Err(error) => Err(ProgramError { line, error }),
+        })
+        .collect()
+}
+
+/// Why an instruction can't be carried out as written
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Invalid {
+    /// 1-based number of a stack that doesn't exist
+    NoSuchStack(usize),
+    NotEnoughCrates {
+        stack: usize,
+        needed: usize,
+        available: usize,
+    },
+    NothingMoved,
+}
+
+/// An instruction that failed strict validation, with the stacks it was given
+#[derive(Clone, Debug, PartialEq)]
+struct ExecutionError {
+    /// The line `parse_program` read the operation from
+    line: usize,
+    operation: Operation,
+    invalid: Invalid,
+    stacks: Stacks,
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {} ({}): ", self.line, self.operation)?;
+        match self.invalid {
+            Invalid::NoSuchStack(stack) => {
+                write!(f, "there is no stack {} of {}", stack, self.stacks.0.len())?
+            }
+            Invalid::NotEnoughCrates {
+                stack,
+                needed,
+                available,
+            } => write!(
+                f,
+                "stack {} has {} crates, not {}",
+                stack, available, needed
+            )?,
+            Invalid::NothingMoved => write!(f, "moves no crates")?,
+        }
+        write!(f, "\n{}", self.stacks)
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+impl Stacks {
+    /// Whether the instruction can be carried out exactly as written
+    fn check(&self, instruction: &Instruction) -> Result<(), Invalid> {
+        for stack in [instruction.from, instruction.to] {
+            if stack >= self.0.len() {
+                return Err(Invalid::NoSuchStack(stack + 1));
+            }
+        }
+        let available = self.0[instruction.from].len();
+        match instruction.amount {
+            0 => Err(Invalid::NothingMoved),
+            needed if needed > available => Err(Invalid::NotEnoughCrates {
+                stack: instruction.from + 1,
+                needed,
+                available,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Moves the crates for an instruction, returning the transfers the crane made
+    fn carry_out<C: Crane + ?Sized>(
+        &mut self,
+        crane: &mut C,
+        instruction: Instruction,
+    ) -> Vec<Transfer> {
+        let transfers = crane.transfers(instruction);
+        for &transfer in &transfers {
+            self.transfer(transfer);
+        }
+        transfers
+    }
+
+    /// Carries out one instruction exactly as written
+    fn try_apply<C: Crane + ?Sized>(
+        &mut self,
+        crane: &mut C,
+        instruction: Instruction,
+    ) -> Result<Vec<Transfer>, Invalid> {
+        self.check(&instruction)?;
+        Ok(self.carry_out(crane, instruction))
+    }
+
+    /// Carries out as much of one instruction as the stacks allow: moves
+    /// involving missing stacks are skipped, and only the crates there are get moved
+    fn apply<C: Crane + ?Sized>(
+        &mut self,
+        crane: &mut C,
+        instruction: Instruction,
+    ) -> Vec<Transfer> {
+        match self.check(&instruction) {
+            Ok(()) => self.carry_out(crane, instruction),
+            Err(Invalid::NotEnoughCrates { available, .. }) => self.carry_out(
+                crane,
+                Instruction {
+                    amount: available,
+                    ..instruction
+                },
+            ),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Runs the instructions, checking each against the stacks as they are by then
+    #[cfg(test)]
+    fn run_checked<C: Crane + ?Sized>(
+        self,
+        crane: &mut C,
+        instructs: &[Instruction],
+    ) -> Result<Self, ExecutionError> {
+        let program: Vec<(usize, Operation)> = (instructs.iter().enumerate())
+            .map(|(k, &instruction)| (k + 1, instruction.into()))
+            .collect();
+        self.run_program_checked(crane, &program)
+    }
+
+    /// Carries out a swap, reverse or rotate, if the stacks it names exist;
+    /// moves are left to `apply`
+    fn rearrange(&mut self, operation: Operation) -> Result<(), Invalid> {
+        let named = match operation {
+            Operation::Swap(a, b) => vec![a, b],
+            Operation::Reverse(a) => vec![a],
+            Operation::Rotate(_) | Operation::Move(_) => vec![],
+        };
+        if let Some(&stack) = named.iter().find(|&&stack| stack >= self.0.len()) {
+            return Err(Invalid::NoSuchStack(stack + 1));
+        }
+        match operation {
+            Operation::Swap(a, b) => self.0.swap(a, b),
+            Operation::Reverse(a) => self.0[a].reverse(),
+            Operation::Move(_) => (),
+            _ if self.0.is_empty() => (),
+            Operation::Rotate(Direction::Left) => self.0.rotate_left(1),
+            Operation::Rotate(Direction::Right) => self.0.rotate_right(1),
+        }
+        Ok(())
+    }
+
+    /// Carries out one operation exactly as written; only moves need the crane
+    fn try_perform<C: Crane + ?Sized>(
+        &mut self,
+        crane: &mut C,
+        operation: Operation,
+    ) -> Result<(), Invalid> {
+        match operation {
+            Operation::Move(instruction) => self.try_apply(crane, instruction).map(|_| ()),
+            _ => self.rearrange(operation),
+        }
+    }
+
+    /// Carries out as much of one operation as the stacks allow, skipping
+    /// swaps and reverses of missing stacks
+    fn perform<C: Crane + ?Sized>(&mut self, crane: &mut C, operation: Operation) {
+        match operation {
+            Operation::Move(instruction) => {
+                self.apply(crane, instruction);
+            }
+            _ => self.rearrange(operation).unwrap_or_default(),
+        }
+    }
+
+    /// Runs a program, each operation as far as it can be
+    fn run_program<C: Crane + ?Sized>(
+        mut self,
+        crane: &mut C,
+        program: &[(usize, Operation)],
+    ) -> Self {
+        for &(_, operation) in program {
+            self.perform(crane, operation);
+        }
+        self
+    }
+
+    /// Runs a program, checking each operation against the stacks as they are
+    /// by then; errors give the line the operation was on
+    fn run_program_checked<C: Crane + ?Sized>(
+        mut self,
+        crane: &mut C,
+        program: &[(usize, Operation)],
+    ) -> Result<Self, ExecutionError> {
+        for &(line, operation) in program {
+            if let Err(invalid) = self.try_perform(crane, operation) {
+                return Err(ExecutionError {
+                    line,
+                    operation,
+                    invalid,
+                    stacks: self,
+                });
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// What the planner should end up with
+#[derive(Clone, Debug, PartialEq)]
+enum Target {
+    /// Exactly these stacks
+    Arrangement(Stacks),
+    /// Stacks whose top crates spell this message
+    Message(String),
+}
+
+impl Target {
+    fn reached(&self, stacks: &Stacks) -> bool {
+        match self {
+            Target::Arrangement(target) => stacks == target,
+            Target::Message(message) => stacks.message() == *message,
+        }
+    }
+
+    /// Whether moving crates around could ever get there; only arrangements
+    /// can be ruled out without searching
+    fn possible(&self, start: &Stacks) -> bool {
+        let sorted = |stacks: &Stacks| {
+            let mut crates: Vec<Crate> = stacks.0.iter().flatten().cloned().collect();
+            crates.sort();
+            crates
+        };
+        match self {
+            Target::Arrangement(target) => {
+                target.0.len() == start.0.len() && sorted(target) == sorted(start)
+            }
+            Target::Message(_) => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PlanError {
+    /// No sequence of moves gets there
+    Unreachable,
+    /// The search gave up after seeing this many different states
+    TooManyStates(usize),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::Unreachable => write!(f, "the target can't be reached"),
+            PlanError::TooManyStates(n) => write!(f, "no plan found within {} states", n),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+impl Stacks {
+    /// Every instruction that can be carried out as written
+    fn moves(&self) -> impl Iterator<Item = Instruction> + '_ {
+        let n = self.0.len();
+        (0..n).flat_map(move |from| {
+            (0..n).filter(move |&to| to != from).flat_map(move |to| {
+                (1..=self.0[from].len()).map(move |amount| Instruction { from, to, amount })
+            })
+        })
+    }
+}
+
+/// The shortest instructions taking the stacks to the target with this crane
+///
+/// A breadth-first search over the arrangements, each seen once along with
+/// the crane's phase, so stateful cranes are planned for exactly. Gives up
+/// after `limit` states, as the number of arrangements grows very quickly
+/// with the number of crates.
+fn plan<C: Crane + Clone>(
+    start: &Stacks,
+    crane: &C,
+    target: &Target,
+    limit: usize,
+) -> Result<Vec<Instruction>, PlanError> {
+    if !target.possible(start) {
+        return Err(PlanError::Unreachable);
+    }
+    // each state with the crane as it is there, and the move that got there first
+    let mut states = vec![(start.clone(), crane.clone(), None)];
+    let mut seen = HashSet::from([(start.clone(), crane.phase())]);
+    let mut next = 0;
+    while next < states.len() {
+        if target.reached(&states[next].0) {
+            let mut instructions = Vec::new();
+            let mut k = next;
+            while let Some((parent, instruction)) = states[k].2 {
+                instructions.push(instruction);
+                k = parent;
+            }
+            instructions.reverse();
+            return Ok(instructions);
+        }
+        let moves: Vec<Instruction> = states[next].0.moves().collect();
+        for instruction in moves {
+            let (mut stacks, mut crane, _) = states[next].clone();
+            stacks.apply(&mut crane, instruction);
+            if seen.insert((stacks.clone(), crane.phase())) {
+                if states.len() == limit {
+                    return Err(PlanError::TooManyStates(limit));
+                }
+                states.push((stacks, crane, Some((next, instruction))));
+            }
+        }
+        next += 1;
+    }
+    Err(PlanError::Unreachable)
+}
+
+/// How much a run of instructions moves, overall and through each stack
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Traffic {
+    instructions: usize,
+    crates: usize,
+    /// Crates lifted off or put onto each stack
+    stacks: Vec<usize>,
+}
+
+impl Traffic {
+    /// Counts the crates the crane actually moves, running the instructions leniently
+    fn of<C: Crane + Clone>(start: &Stacks, crane: &C, instructs: &[Instruction]) -> Traffic {
+        let (mut stacks, mut crane) = (start.clone(), crane.clone());
+        let mut traffic = Traffic {
+            instructions: instructs.len(),
+            crates: 0,
+            stacks: vec![0; start.0.len()],
+        };
+        for &instruction in instructs {
+            for transfer in stacks.apply(&mut crane, instruction) {
+                traffic.crates += transfer.count;
+                traffic.stacks[transfer.from] += transfer.count;
+                traffic.stacks[transfer.to] += transfer.count;
+            }
+        }
+        traffic
+    }
+
+    /// Up to n of the stacks with the most traffic, as (1-based stack, crates)
+    fn busiest(&self, n: usize) -> Vec<(usize, usize)> {
+        let mut busiest: Vec<(usize, usize)> = (self.stacks.iter().enumerate())
+            .filter(|&(_, &crates)| crates > 0)
+            .map(|(k, &crates)| (k + 1, crates))
+            .collect();
+        busiest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        busiest.truncate(n);
+        busiest
+    }
+}
+
+impl fmt::Display for Traffic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let busiest: Vec<String> = (self.busiest(3).iter())
+            .map(|(stack, crates)| format!("{} ({})", stack, crates))
+            .collect();
+        write!(
+            f,
+            "{} instructions moving {} crates, busiest stacks {}",
+            self.instructions,
+            self.crates,
+            busiest.join(", ")
+        )
+    }
+}
+
+/// Shorter instructions leaving the stacks exactly as the given ones do
+///
+/// Each instruction is merged with the one before when they move between the
+/// same stacks, dropped along with it when it puts the crates back, and
+/// dropped alone when it changes nothing. A rewrite is only kept if it gives
+/// the same stacks, and crane phase, as the instructions it replaces, so what
+/// merges depends on the crane: a CrateMover 9000 can always merge, a 9001
+/// only when the order of the crates doesn't matter.
+fn optimize<C: Crane + Clone>(
+    start: &Stacks,
+    crane: &C,
+    instructs: &[Instruction],
+) -> Vec<Instruction> {
+    let step = |(stacks, crane): &(Stacks, C), instruction: Instruction| {
+        let (mut stacks, mut crane) = (stacks.clone(), crane.clone());
+        stacks.apply(&mut crane, instruction);
+        (stacks, crane)
+    };
+    let same = |a: &(Stacks, C), b: &(Stacks, C)| a.0 == b.0 && a.1.phase() == b.1.phase();
+
+    let mut kept: Vec<Instruction> = Vec::new();
+    // states[k] is the state before kept[k], and the last one after all of them
+    let mut states = vec![(start.clone(), crane.clone())];
+    for &instruction in instructs {
+        let mut next = instruction;
+        loop {
+            let now = &states[states.len() - 1];
+            let after = step(now, next);
+            if same(now, &after) {
+                break;
+            }
+            if let Some(&last) = kept.last() {
+                let before = &states[states.len() - 2];
+                let merged = Instruction {
+                    amount: last.amount + next.amount,
+                    ..last
+                };
+                let cancels = same(before, &after);
+                let merges = !cancels
+                    && (last.from, last.to) == (next.from, next.to)
+                    && same(&step(before, merged), &after);
+                if cancels || merges {
+                    kept.pop();
+                    states.pop();
+                }
+                if cancels {
+                    break;
+                }
+                if merges {
+                    // the merged move may now combine with the one before
+                    next = merged;
+                    continue;
+                }
+            }
+            kept.push(next);
+            states.push(after);
+            break;
+        }
+    }
+    kept
+}
+
+fn main() {
+    let binding = include_str!("../input.txt").lines().collect::<Vec<&str>>();
+    let contents: Vec<&[&str]> = binding.split(|v| v.is_empty()).collect();
+    let stacks: Stacks = match contents[0].join("\n").parse() {
+        Ok(stacks) => stacks,
+        Err(e) => panic!("Cannot parse stack diagram: {}", e),
+    };
+
+    // the program starts after the diagram and the blank line
+    let first_line = contents[0].len() + 2;
+    let program = match parse_program(&binding[first_line - 1..].join("\n"), first_line) {
+        Ok(program) => program,
+        Err(e) => panic!("{}", e),
+    };
+    // stepping through and optimizing work on plain moves only
+    let instructions: Option<Vec<Instruction>> = program
+        .iter()
+        .map(|(_, operation)| operation.as_move())
+        .collect();
+    let lenient = |crane: &mut dyn Crane| stacks.clone().run_program(crane, &program);
+
+    // `day5 step [9001]` redraws the stacks after every instruction: Enter
+    // goes forward, "b" back, a number jumps there and "q" quits
+    if std::env::args().nth(1).as_deref() == Some("step") {
+        let Some(instructions) = &instructions else {
+            println!("Only programs of moves can be stepped through");
+            return;
         };
-        let amount = get_bit(1);
-        let from = get_bit(3) - 1; // subtract 1 for correct indexing
-        let to = get_bit(5) - 1;
-        Instruction { from, to, amount }
-    }
-}
-
-impl Instruction {
-    fn execute_2(self, stacks: Stacks) -> Stacks {
-        // println!("Instruct: {:?}\nStart:\n{:?}\n", self, stacks);
-        let (mut stacks_temp, mut temps) = (0..self.amount)
-            .fold((stacks, Vec::new()), |(mut cs, mut temps), _| {
-            if let Some(top) = cs.0[self.from].pop() {
-                temps.push(top)
+        let mut crane: Box<dyn Crane> = match std::env::args().nth(2).as_deref() {
+            Some("9001") => Box::new(CrateMover9001),
+            _ => Box::new(CrateMover9000),
+        };
+        let mut history = History::record(stacks.clone(), crane.as_mut(), instructions);
+        history.jump(0);
+        let clear = match std::io::stdout().is_terminal() {
+            true => "\x1b[2J\x1b[H",
+            false => "",
+        };
+        loop {
+            match history.last() {
+                Some(i) => print!(
+                    "{}Step {}/{}: {}\n\n{}\n",
+                    clear,
+                    history.position,
+                    history.len(),
+                    i,
+                    history.stacks
+                ),
+                None => print!("{}Start\n\n{}\n", clear, history.stacks),
             }
-            // println!("{:?}\n", cs);
-            (cs, temps)
-        });
-
-        temps.reverse();
-        stacks_temp.0[self.to].append(&mut temps);
-        // println!("{:?}\n", stacks_temp);
-        stacks_temp
+            let mut command = String::new();
+            if std::io::stdin().read_line(&mut command).unwrap_or(0) == 0 {
+                return;
+            }
+            match command.trim() {
+                "q" => return,
+                "b" => {
+                    history.back();
+                }
+                "" | "n" => {
+                    if !history.forward() {
+                        return;
+                    }
+                }
+                n => match n.parse() {
+                    Ok(n) => history.jump(n),
+                    Err(_) => println!("Unknown command {:?}", n),
+                },
+            }
+        }
     }
 
-    fn execute(self, stacks: Stacks) -> Stacks {
-        // println!("Instruct: {:?}\nStart:\n{:?}\n", self, stacks);
-        (0..self.amount).fold(stacks, |mut cs, _| {
-            if let Some(top) = cs.0[self.from].pop() {
-                cs.0[self.to].push(top);
+    // `day5 plan [MESSAGE] [9001]` finds the fewest moves giving that message,
+    // or the arrangement the instructions end with if there is none
+    if std::env::args().nth(1).as_deref() == Some("plan") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        let is_9001 = args.iter().any(|a| a == "9001");
+        let target = match args.iter().find(|a| *a != "9001") {
+            Some(message) => Target::Message(message.clone()),
+            None => Target::Arrangement(match is_9001 {
+                true => lenient(&mut CrateMover9001),
+                false => lenient(&mut CrateMover9000),
+            }),
+        };
+        let found = match is_9001 {
+            true => plan(&stacks, &CrateMover9001, &target, 1_000_000),
+            false => plan(&stacks, &CrateMover9000, &target, 1_000_000),
+        };
+        match found {
+            Ok(instructions) => {
+                println!("{} moves", instructions.len());
+                for instruction in instructions {
+                    println!("{}", instruction);
+                }
             }
-            // println!("{:?}\n", cs);
-            cs
-        })
+            Err(e) => println!("No plan: {}", e),
+        }
+        return;
     }
-}
 
-fn main() {
-    let binding = include_str!("../input.txt").lines().collect::<Vec<&str>>();
-    let contents: Vec<&[&str]> = binding.split(|&v| v == "").collect();
-    let stack_str: Vec<Vec<u8>> = contents[0]
-        .into_iter()
-        .map(|line| line.as_bytes().to_vec())
-        .collect();
-    let stacks: Stacks = Stacks(
-        transpose(stack_str)
-            .into_iter()
-            .filter(|stack| stack[0] != 32)
-            .map(|stack| {
-                stack[1..]
-                    .to_vec()
-                    .into_iter()
-                    .filter(|&i| i != 32)
-                    .collect::<Vec<u8>>()
-            })
-            .collect(),
-    );
+    let strict = |crane: &mut dyn Crane| {
+        stacks
+            .clone()
+            .run_program_checked(crane, &program)
+            .map(|s| s.message())
+            .unwrap_or_else(|e| format!("invalid instructions\n{}", e))
+    };
+    println!("Message: {}", strict(&mut CrateMover9000));
+    println!("Message 2: {}", strict(&mut CrateMover9001));
 
-    let instructions: Vec<Instruction> = contents[1].into_iter().map(|&line| line.into()).collect();
+    if let Some(instructions) = &instructions {
+        let optimized = optimize(&stacks, &CrateMover9000, instructions);
+        println!(
+            "CrateMover 9000 instructions: {}",
+            Traffic::of(&stacks, &CrateMover9000, instructions)
+        );
+        println!(
+            "Optimized: {}",
+            Traffic::of(&stacks, &CrateMover9000, &optimized)
+        );
+        let optimized = optimize(&stacks, &CrateMover9001, instructions);
+        println!(
+            "CrateMover 9001 instructions: {}",
+            Traffic::of(&stacks, &CrateMover9001, instructions)
+        );
+        println!(
+            "Optimized: {}",
+            Traffic::of(&stacks, &CrateMover9001, &optimized)
+        );
+    }
 
-    println!("Message: {}", stacks.clone().execute(instructions.clone()).message());
-    println!("Message 2: {}", stacks.execute_2(instructions).message());
+    let cranes: Vec<Box<dyn Crane>> = vec![
+        Box::new(CrateMover9000),
+        Box::new(CrateMover9001),
+        Box::new(LimitedLift { capacity: 3 }),
+        Box::new(Chunked { size: 2 }),
+        Box::new(Alternating::default()),
+    ];
+    for mut crane in cranes {
+        let message = lenient(crane.as_mut()).message();
+        println!("{}: {}", crane.name(), message);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use useful::Random;
 
     fn example_contents() -> Vec<Vec<String>> {
         "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\nmove 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2"
             .lines()
             .map(|s| s.to_string())
             .collect::<Vec<String>>()
-            .split(|v| v == "")
+            .split(|v| v.is_empty())
             .map(|chunk| chunk.to_vec())
             .collect()
     }
 
     fn example_stacks() -> Stacks {
-        let stack_str: Vec<Vec<u8>> = example_contents()[0]
-            .iter()
-            .map(|line| line.as_bytes().to_vec())
-            .collect();
-        let stacks: Stacks = Stacks(
-            transpose(stack_str)
-                .into_iter()
-                .filter(|stack| stack[0] != 32)
-                .map(|stack| {
-                    stack[1..]
-                        .to_vec()
-                        .into_iter()
-                        .filter(|&i| i != 32)
-                        .collect::<Vec<u8>>()
-                })
-                .collect(),
-        );
+        example_contents()[0].join("\n").parse().unwrap()
+    }
 
-        stacks
+    /// A crate for each character
+    fn crates(labels: &str) -> Vec<Crate> {
+        labels
+            .chars()
+            .map(|c| Crate::new(&c.to_string()).unwrap())
+            .collect()
     }
 
     fn example_instructions() -> Vec<Instruction> {
@@ -173,54 +1428,32 @@ mod tests {
             .collect()
     }
 
-    fn correct_stacks() -> Stacks {
-        let stack_str: Vec<Vec<u8>> =
-            "        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 "
-                .lines()
-                .map(|s| s.to_string())
-                // .collect::<Vec<String>>()
-                .map(|line| line.as_bytes().to_vec())
-                .collect();
-        let stacks: Stacks = Stacks(
-            transpose(stack_str)
-                .into_iter()
-                .filter(|stack| stack[0] != 32)
-                .map(|stack| {
-                    stack[1..]
-                        .to_vec()
-                        .into_iter()
-                        .filter(|&i| i != 32)
-                        .collect::<Vec<u8>>()
-                })
-                .collect(),
+    /// Optimized instructions, checked to leave the stacks as the given ones do
+    fn optimized<C: Crane + Clone>(
+        start: &Stacks,
+        crane: &C,
+        instructs: &[Instruction],
+    ) -> Vec<Instruction> {
+        let kept = optimize(start, crane, instructs);
+        let ran = |instructs: &[Instruction]| start.clone().run(&mut crane.clone(), instructs);
+        assert_eq!(
+            ran(instructs),
+            ran(&kept),
+            "Optimized instructions end differently"
         );
+        kept
+    }
 
-        stacks
+    fn correct_stacks() -> Stacks {
+        "        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 "
+            .parse()
+            .unwrap()
     }
 
     fn correct_stacks_2() -> Stacks {
-        let stack_str: Vec<Vec<u8>> =
-            "        [D]\n        [N]\n        [Z]\n[M] [C] [P]\n 1   2   3 "
-                .lines()
-                .map(|s| s.to_string())
-                // .collect::<Vec<String>>()
-                .map(|line| line.as_bytes().to_vec())
-                .collect();
-        let stacks: Stacks = Stacks(
-            transpose(stack_str)
-                .into_iter()
-                .filter(|stack| stack[0] != 32)
-                .map(|stack| {
-                    stack[1..]
-                        .to_vec()
-                        .into_iter()
-                        .filter(|&i| i != 32)
-                        .collect::<Vec<u8>>()
-                })
-                .collect(),
-        );
-
-        stacks
+        "        [D]\n        [N]\n        [Z]\n[M] [C] [P]\n 1   2   3 "
+            .parse()
+            .unwrap()
     }
 
     #[test]
@@ -254,4 +1487,661 @@ mod tests {
             example_stacks().execute_2(example_instructions()).message(),
         );
     }
+
+    #[test]
+    fn parse_ragged_diagram() {
+        // editors strip the trailing spaces after "[D]" and "[C]"
+        assert_eq!(
+            example_stacks(),
+            "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3".parse().unwrap()
+        );
+        assert_eq!(
+            Stacks(vec![crates("ZN"), crates("MCD"), crates("P")]),
+            example_stacks()
+        );
+    }
+
+    #[test]
+    fn parse_many_stacks() {
+        let diagram = "                                        [K]\n\
+                       [A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [L] [M]\n \
+                        1   2   3   4   5   6   7   8   9  10  11  12 ";
+        let stacks: Stacks = diagram.parse().unwrap();
+        assert_eq!(12, stacks.0.len());
+        assert_eq!(crates("J"), stacks.0[9]);
+        assert_eq!(crates("LK"), stacks.0[10]);
+        assert_eq!("ABCDEFGHIJKM", stacks.message());
+    }
+
+    #[test]
+    fn parse_diagram_errors() {
+        let parse = |s: &str| s.parse::<Stacks>();
+        assert_eq!(Err(DiagramError::NoFooter), parse("  \n"));
+        assert_eq!(
+            Err(DiagramError::BadLabel {
+                column: 6,
+                label: "3".into()
+            }),
+            parse("[A] [B]\n 1   3")
+        );
+        assert_eq!(
+            Err(DiagramError::Misaligned { line: 1, column: 2 }),
+            parse(" [A]\n 1   2")
+        );
+        assert_eq!(
+            Err(DiagramError::Unclosed { line: 2, column: 5 }),
+            parse("[A]\n[B] [C\n 1   2")
+        );
+        assert_eq!(
+            Err(DiagramError::BadCrate {
+                line: 1,
+                column: 1,
+                label: "".into()
+            }),
+            parse("[]\n 1")
+        );
+        assert_eq!(
+            Err(DiagramError::Unexpected {
+                line: 1,
+                column: 5,
+                found: 'x'
+            }),
+            parse("[A] x\n 1")
+        );
+        assert_eq!(
+            Err(DiagramError::Floating { line: 1, stack: 2 }),
+            parse("    [A]\n[B]\n 1   2")
+        );
+    }
+
+    #[test]
+    fn render_round_trip() {
+        let diagram = example_contents()[0].join("\n");
+        assert_eq!(diagram, example_stacks().to_string());
+        assert_eq!(
+            "        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 ",
+            correct_stacks().to_string()
+        );
+        let many: Stacks = "                                        [K]\n\
+                            [A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [L]\n \
+                             1   2   3   4   5   6   7   8   9  10  11 "
+            .parse()
+            .unwrap();
+        assert_eq!(many, many.to_string().parse().unwrap());
+        assert_eq!(" 1   2 ", Stacks(vec![vec![], vec![]]).to_string());
+    }
+
+    #[test]
+    fn step_through_history() {
+        let mut history = History::record(
+            example_stacks(),
+            &mut CrateMover9000,
+            &example_instructions(),
+        );
+        assert_eq!(correct_stacks(), history.stacks);
+        assert!(!history.forward());
+
+        history.jump(1);
+        assert_eq!(
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ",
+            history.stacks.to_string()
+        );
+        assert_eq!("move 1 from 2 to 1", history.last().unwrap().to_string());
+        assert!(history.back());
+        assert_eq!(example_stacks(), history.stacks);
+        assert_eq!(None, history.last());
+        assert!(!history.back());
+        history.jump(99);
+        assert_eq!(correct_stacks(), history.stacks);
+    }
+
+    #[test]
+    fn history_matches_every_state() {
+        let cranes: [fn() -> Box<dyn Crane>; 4] = [
+            || Box::new(CrateMover9000),
+            || Box::new(CrateMover9001),
+            || Box::new(Chunked { size: 2 }),
+            || Box::new(Alternating::default()),
+        ];
+        // the last two only move what there is, so undoing them must too
+        let mut instructions = example_instructions();
+        instructions.extend(["move 9 from 3 to 2", "move 4 from 2 to 7"].map(Instruction::from));
+        for crane in cranes {
+            let mut states = vec![example_stacks()];
+            let mut running = crane();
+            for &instruction in instructions.iter() {
+                let mut next = states.last().unwrap().clone();
+                next.apply(running.as_mut(), instruction);
+                states.push(next);
+            }
+
+            let mut history = History::record(example_stacks(), crane().as_mut(), &instructions);
+            for n in [6, 0, 3, 5, 1, 4, 2, 6, 0] {
+                history.jump(n);
+                assert_eq!(states[n], history.stacks, "{} at {}", running.name(), n);
+            }
+        }
+    }
+
+    #[test]
+    fn crane_models() {
+        let run = |crane: &mut dyn Crane| example_stacks().run(crane, &example_instructions());
+        assert_eq!(correct_stacks(), run(&mut CrateMover9000));
+        assert_eq!(correct_stacks_2(), run(&mut CrateMover9001));
+        assert_eq!("MCD", run(&mut LimitedLift { capacity: 3 }).message());
+        // only "move 3 from 1 to 3" is too heavy for it
+        assert_eq!("MCZ", run(&mut LimitedLift { capacity: 2 }).message());
+        assert_eq!("CMZ", run(&mut LimitedLift { capacity: 1 }).message());
+        assert_eq!(correct_stacks(), run(&mut Chunked { size: 1 }));
+        assert_eq!(correct_stacks_2(), run(&mut Chunked { size: 3 }));
+        // moving Z N D from stack 1 takes N D first, then Z
+        assert_eq!(
+            "        [Z]\n        [D]\n        [N]\n[M] [C] [P]\n 1   2   3 ",
+            run(&mut Chunked { size: 2 }).to_string()
+        );
+        assert_eq!(
+            "        [Z]\n        [N]\n        [D]\n[M] [C] [P]\n 1   2   3 ",
+            run(&mut Alternating::default()).to_string()
+        );
+    }
+
+    #[test]
+    fn parse_instruction_errors() {
+        assert_eq!(
+            Ok(Instruction {
+                from: 1,
+                to: 0,
+                amount: 0
+            }),
+            "move 0 from 2 to 1".parse()
+        );
+        assert_eq!(
+            Err(ParseInstructionError::StackZero(
+                "move 1 from 0 to 1".into()
+            )),
+            "move 1 from 0 to 1".parse::<Instruction>()
+        );
+        assert_eq!(
+            Err(ParseInstructionError::Number("x".into())),
+            "move x from 1 to 2".parse::<Instruction>()
+        );
+        assert_eq!(
+            Err(ParseInstructionError::Expected {
+                expected: "from",
+                found: Some("to".into())
+            }),
+            "move 1 to 2".parse::<Instruction>()
+        );
+    }
+
+    #[test]
+    fn parse_operations() {
+        let program = "# shuffle the example\n\
+                       move 1 from 2 to 1   # one crate\n\
+                       \n\
+                       swap 1 3\n\
+                       reverse 2\n\
+                       rotate left\n\
+                       rotate right";
+        let operations = vec![
+            Operation::Move("move 1 from 2 to 1".into()),
+            Operation::Swap(0, 2),
+            Operation::Reverse(1),
+            Operation::Rotate(Direction::Left),
+            Operation::Rotate(Direction::Right),
+        ];
+        assert_eq!(
+            Ok([2, 4, 5, 6, 7]
+                .into_iter()
+                .zip(operations.clone())
+                .collect()),
+            parse_program(program, 1)
+        );
+        for operation in operations {
+            assert_eq!(Ok(operation), operation.to_string().parse());
+        }
+
+        let error = |line: &str| line.parse::<Operation>().unwrap_err();
+        assert_eq!(
+            ParseInstructionError::UnknownOperation("jump".into()),
+            error("jump 1 2")
+        );
+        assert_eq!(
+            ParseInstructionError::Expected {
+                expected: "a stack number",
+                found: None
+            },
+            error("swap 1 # 2")
+        );
+        assert_eq!(
+            ParseInstructionError::Expected {
+                expected: "left or right",
+                found: Some("up".into())
+            },
+            error("rotate up")
+        );
+        assert_eq!(
+            ParseInstructionError::Trailing("3".into()),
+            error("reverse 2 3")
+        );
+        assert_eq!(
+            ParseInstructionError::StackZero("reverse 0".into()),
+            error("reverse 0")
+        );
+        assert_eq!(
+            Err(ParseInstructionError::NotAMove(Operation::Reverse(0))),
+            "reverse 1".parse::<Instruction>()
+        );
+        assert_eq!(
+            Err(ProgramError {
+                line: 3,
+                error: ParseInstructionError::Number("two".into())
+            }),
+            parse_program("rotate left\n\nmove two from 1 to 2", 1)
+        );
+        assert_eq!(
+            "Line 8: Cannot parse number \"two\"",
+            parse_program("rotate left\n\nmove two from 1 to 2", 6)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn run_operations() {
+        let run = |program: &str| {
+            example_stacks()
+                .run_program_checked(&mut CrateMover9001, &parse_program(program, 1).unwrap())
+        };
+        let stacks = |rows: [&str; 3]| Stacks(rows.iter().map(|r| crates(r)).collect());
+
+        assert_eq!(Ok(stacks(["P", "MCD", "ZN"])), run("swap 1 3"));
+        assert_eq!(Ok(stacks(["ZN", "DCM", "P"])), run("reverse 2"));
+        assert_eq!(Ok(stacks(["MCD", "P", "ZN"])), run("rotate left"));
+        assert_eq!(Ok(stacks(["P", "ZN", "MCD"])), run("rotate right"));
+        assert_eq!(
+            Ok(example_stacks()),
+            run("rotate left\nrotate left\nrotate left")
+        );
+        assert_eq!(
+            Ok(stacks(["ZNCM", "D", "P"])),
+            run("reverse 2\nmove 2 from 2 to 1")
+        );
+
+        // missing stacks fail strictly, and are skipped leniently
+        let error = run("swap 1 2\nreverse 4").unwrap_err();
+        assert_eq!(
+            (2, Invalid::NoSuchStack(4)),
+            (error.line, error.invalid.clone())
+        );
+        assert!(error
+            .to_string()
+            .starts_with("Line 2 (reverse 4): there is no stack 4 of 3"));
+
+        // comment and blank lines still count towards the line reported
+        let error = run("# c\n\nmove 1 from 1 to 2\nmove 5 from 1 to 2").unwrap_err();
+        assert_eq!(
+            (
+                4,
+                Invalid::NotEnoughCrates {
+                    stack: 1,
+                    needed: 5,
+                    available: 1
+                }
+            ),
+            (error.line, error.invalid.clone())
+        );
+        assert_eq!(
+            stacks(["MCD", "ZN", "P"]),
+            example_stacks().run_program(
+                &mut CrateMover9001,
+                &parse_program("swap 1 2\nreverse 4\nswap 3 5", 1).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn strict_validation() {
+        let strict = |line: &str| {
+            example_stacks().run_checked(
+                &mut CrateMover9000,
+                &[example_instructions()[0], line.into()],
+            )
+        };
+        let after_first = example_stacks().execute(example_instructions()[..1].to_vec());
+        assert_eq!(
+            Err(ExecutionError {
+                line: 2,
+                operation: Operation::Move("move 4 from 1 to 2".into()),
+                invalid: Invalid::NotEnoughCrates {
+                    stack: 1,
+                    needed: 4,
+                    available: 3
+                },
+                stacks: after_first.clone(),
+            }),
+            strict("move 4 from 1 to 2")
+        );
+        assert_eq!(
+            Invalid::NoSuchStack(4),
+            strict("move 1 from 1 to 4").unwrap_err().invalid
+        );
+        assert_eq!(
+            Invalid::NothingMoved,
+            strict("move 0 from 1 to 2").unwrap_err().invalid
+        );
+        assert_eq!(
+            "Line 2 (move 1 from 4 to 1): there is no stack 4 of 3\n\
+             [D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ",
+            strict("move 1 from 4 to 1").unwrap_err().to_string()
+        );
+        assert_eq!(
+            Ok(correct_stacks()),
+            example_stacks().run_checked(&mut CrateMover9000, &example_instructions(),)
+        );
+    }
+
+    #[test]
+    fn lenient_validation() {
+        let lenient = |line: &str| example_stacks().run(&mut CrateMover9001, &[line.into()]);
+        assert_eq!(example_stacks(), lenient("move 1 from 1 to 4"));
+        assert_eq!(example_stacks(), lenient("move 0 from 1 to 2"));
+        assert_eq!(
+            "        [D]\n        [C]\n[N]     [M]\n[Z]     [P]\n 1   2   3 ",
+            lenient("move 5 from 2 to 3").to_string()
+        );
+    }
+
+    // the cranes as they were before bulk moves, popping one crate at a time
+    fn pop_9000(mut stacks: Stacks, instruction: Instruction) -> Stacks {
+        for _ in 0..instruction.amount {
+            if let Some(top) = stacks.0[instruction.from].pop() {
+                stacks.0[instruction.to].push(top);
+            }
+        }
+        stacks
+    }
+
+    fn pop_9001(mut stacks: Stacks, instruction: Instruction) -> Stacks {
+        let mut temps = Vec::new();
+        for _ in 0..instruction.amount {
+            if let Some(top) = stacks.0[instruction.from].pop() {
+                temps.push(top)
+            }
+        }
+        temps.reverse();
+        stacks.0[instruction.to].append(&mut temps);
+        stacks
+    }
+
+    #[test]
+    fn bulk_moves_match_popping() {
+        let letters = crates("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+        let mut random = Random::new(5);
+        let mut next = |n| random.below(n);
+        for _ in 0..50 {
+            let count = next(6) + 1;
+            let stacks = Stacks(
+                (0..count)
+                    .map(|_| (0..next(8)).map(|_| letters[next(26)].clone()).collect())
+                    .collect(),
+            );
+            let (mut ours, mut ours_2) = (stacks.clone(), stacks.clone());
+            let (mut theirs, mut theirs_2) = (stacks.clone(), stacks);
+            for _ in 0..40 {
+                // includes moves of more crates than there are, and onto the same stack
+                let instruction = Instruction {
+                    from: next(count),
+                    to: next(count),
+                    amount: next(10),
+                };
+                for transfer in CrateMover9000.transfers(instruction) {
+                    ours.transfer(transfer);
+                }
+                for transfer in CrateMover9001.transfers(instruction) {
+                    ours_2.transfer(transfer);
+                }
+                theirs = pop_9000(theirs, instruction);
+                theirs_2 = pop_9001(theirs_2, instruction);
+                assert_eq!(theirs, ours, "{}", instruction);
+                assert_eq!(theirs_2, ours_2, "{}", instruction);
+            }
+        }
+    }
+
+    #[test]
+    fn bulk_moves_of_millions() {
+        let letters = crates("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+        let crates = 3_000_000;
+        let stacks = Stacks(vec![
+            (0..crates).map(|k| letters[k % 26].clone()).collect(),
+            vec![],
+        ]);
+        let there = Instruction {
+            from: 0,
+            to: 1,
+            amount: crates,
+        };
+        let back = Instruction {
+            from: 1,
+            to: 0,
+            amount: crates,
+        };
+        let moved = stacks
+            .clone()
+            .run(&mut CrateMover9001, &[there, back, there, back]);
+        assert_eq!(stacks, moved);
+        let moved = stacks.clone().run(&mut CrateMover9000, &[there, back]);
+        assert_eq!(stacks, moved);
+    }
+
+    #[test]
+    fn wide_crate_labels() {
+        let diagram = "          [B2]\n[A]       [12]\n[AB]      [C]  [x]\n 1    2    3    4 ";
+        let stacks: Stacks = diagram.parse().unwrap();
+        assert_eq!(
+            vec!["AB", "A"],
+            stacks.0[0]
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert!(stacks.0[1].is_empty());
+        assert_eq!("A B2 x", stacks.message_with(" "));
+        assert_eq!("AB2x", stacks.message());
+
+        // rendered with every column as wide as the widest label
+        let rendered =
+            "          [B2]     \n[A ]      [12]     \n[AB]      [C ] [x ]\n 1    2    3    4  ";
+        assert_eq!(rendered, stacks.to_string());
+        assert_eq!(stacks, rendered.parse().unwrap());
+
+        let moved = stacks.run(&mut CrateMover9001, &["move 2 from 3 to 2".into()]);
+        assert_eq!("A-B2-C-x", moved.message_with("-"));
+    }
+
+    #[test]
+    fn plan_shortest_moves() {
+        let one = example_stacks().execute(vec!["move 1 from 2 to 1".into()]);
+        let found = plan(
+            &example_stacks(),
+            &CrateMover9000,
+            &Target::Arrangement(one),
+            1000,
+        );
+        assert_eq!(Ok(vec!["move 1 from 2 to 1".into()]), found);
+        assert_eq!(
+            Ok(vec![]),
+            plan(
+                &example_stacks(),
+                &CrateMover9000,
+                &Target::Message("NDP".into()),
+                1000
+            )
+        );
+
+        let found = plan(
+            &example_stacks(),
+            &CrateMover9000,
+            &Target::Arrangement(correct_stacks()),
+            100_000,
+        )
+        .unwrap();
+        // one move fewer than the puzzle's instructions
+        assert_eq!(3, found.len());
+        assert_eq!(correct_stacks(), example_stacks().execute(found));
+
+        let found = plan(
+            &example_stacks(),
+            &CrateMover9001,
+            &Target::Message("MCD".into()),
+            100_000,
+        )
+        .unwrap();
+        assert_eq!(3, found.len());
+        assert_eq!("MCD", example_stacks().execute_2(found).message());
+    }
+
+    #[test]
+    fn plan_for_stateful_crane() {
+        let target = example_stacks().run(&mut Alternating::default(), &example_instructions());
+        let found = plan(
+            &example_stacks(),
+            &Alternating::default(),
+            &Target::Arrangement(target.clone()),
+            100_000,
+        )
+        .unwrap();
+        assert_eq!(4, found.len());
+        assert_eq!(
+            target,
+            example_stacks().run(&mut Alternating::default(), &found)
+        );
+    }
+
+    #[test]
+    fn plan_errors() {
+        let mut other = example_stacks();
+        other.0[0][0] = Crate::new("X").unwrap();
+        let start = example_stacks();
+        assert_eq!(
+            Err(PlanError::Unreachable),
+            plan(&start, &CrateMover9000, &Target::Arrangement(other), 1000)
+        );
+        assert_eq!(
+            Err(PlanError::Unreachable),
+            plan(
+                &start,
+                &CrateMover9000,
+                &Target::Message("NDPQ".into()),
+                1_000_000
+            )
+        );
+        assert_eq!(
+            Err(PlanError::TooManyStates(5)),
+            plan(
+                &start,
+                &CrateMover9000,
+                &Target::Arrangement(correct_stacks()),
+                5
+            )
+        );
+    }
+
+    #[test]
+    fn optimize_merges_and_cancels() {
+        let stacks: Stacks = "[A]\n[B]\n[C]     [D]\n 1   2   3".parse().unwrap();
+        let instructions = |lines: &[&str]| -> Vec<Instruction> {
+            lines.iter().map(|&line| line.into()).collect()
+        };
+
+        // a 9000 moves one crate at a time anyway
+        let merging = instructions(&["move 1 from 1 to 2", "move 2 from 1 to 2"]);
+        assert_eq!(
+            instructions(&["move 3 from 1 to 2"]),
+            optimized(&stacks, &CrateMover9000, &merging)
+        );
+        // while a 9001 would stack them in a different order
+        assert_eq!(merging, optimized(&stacks, &CrateMover9001, &merging));
+
+        // putting crates back cancels out, even once nested
+        let returning = instructions(&[
+            "move 1 from 1 to 2",
+            "move 1 from 2 to 3",
+            "move 1 from 3 to 2",
+            "move 1 from 2 to 1",
+            "move 1 from 3 to 1",
+        ]);
+        let expected = instructions(&["move 1 from 3 to 1"]);
+        assert_eq!(expected, optimized(&stacks, &CrateMover9000, &returning));
+        assert_eq!(expected, optimized(&stacks, &CrateMover9001, &returning));
+
+        // moves that do nothing are dropped, and merged moves keep merging
+        let idle = instructions(&[
+            "move 1 from 2 to 3",
+            "move 1 from 1 to 3",
+            "move 1 from 1 to 3",
+            "move 1 from 4 to 1",
+            "move 1 from 1 to 3",
+        ]);
+        assert_eq!(
+            instructions(&["move 3 from 1 to 3"]),
+            optimized(&stacks, &CrateMover9000, &idle)
+        );
+
+        // the example has nothing to improve
+        let example = example_instructions();
+        assert_eq!(
+            example,
+            optimized(&example_stacks(), &CrateMover9000, &example)
+        );
+        assert_eq!(
+            example,
+            optimized(&example_stacks(), &CrateMover9001, &example)
+        );
+    }
+
+    #[test]
+    fn optimize_random_runs() {
+        let stacks: Stacks = "[A] [D]\n[B] [E]\n[C] [F] [G]\n 1   2   3".parse().unwrap();
+        // moves between few stacks, so plenty of them merge or cancel
+        let mut random = Random::new(7);
+        for _ in 0..50 {
+            let instructs: Vec<Instruction> = (0..40)
+                .map(|_| {
+                    let from = random.below(3);
+                    Instruction {
+                        from,
+                        to: (from + 1 + random.below(2)) % 3,
+                        amount: 1 + random.below(3),
+                    }
+                })
+                .collect();
+            let shorter = |n: usize| assert!(n <= instructs.len());
+            shorter(optimized(&stacks, &CrateMover9000, &instructs).len());
+            shorter(optimized(&stacks, &CrateMover9001, &instructs).len());
+            shorter(optimized(&stacks, &Alternating::default(), &instructs).len());
+            shorter(optimized(&stacks, &Chunked { size: 2 }, &instructs).len());
+        }
+    }
+
+    #[test]
+    fn traffic_statistics() {
+        let traffic = Traffic::of(&example_stacks(), &CrateMover9000, &example_instructions());
+        assert_eq!(4, traffic.instructions);
+        assert_eq!(7, traffic.crates);
+        assert_eq!(vec![7, 4, 3], traffic.stacks);
+        assert_eq!(vec![(1, 7), (2, 4)], traffic.busiest(2));
+        assert_eq!(
+            "4 instructions moving 7 crates, busiest stacks 1 (7), 2 (4), 3 (3)",
+            traffic.to_string()
+        );
+
+        // lenient runs count the crates there were
+        let short = vec!["move 5 from 3 to 1".into()];
+        assert_eq!(
+            1,
+            Traffic::of(&example_stacks(), &CrateMover9001, &short).crates
+        );
+    }
 }
@@ -1,79 +1,660 @@
-fn markers(chars: &[u8], u: usize) -> Vec<(usize, u8)> {
-    chars
-        .iter()
-        .enumerate()
-        .filter_map(|(i, c)| match (0..u).contains(&i) {
-            true => None,
-            false => {
-                let non_u: Vec<u8> = chars[i - u..i]
-                    .iter()
-                    .enumerate()
-                    .filter(|(j, h)| chars[i-u..i-u+j].contains(h))
-                    .map(|t| t.1)
-                    .copied()
-                    .collect();
-/*                if chars[i - u..=i]
-                    .iter()
-                    .enumerate()
-                    .all(|(j, h)| !&chars[i-u..i-u+j].contains(h))
-                    */
-                if non_u.len() == 0
-                {
-                    println!("Yep:{} {}", i, chars[i-u..=i].iter().map(|&c| c as char).collect::<String>());
-                    Some((i, c.to_owned()))
-                } else {
-                    println!("Bruh: {} - {}", non_u.iter().map(|&c| c as char).collect::<String>(),
-                    chars[i-u..=i].iter().map(|&c| c as char).collect::<String>());
-                    None
-                }
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, ErrorKind, Read};
+use std::marker::PhantomData;
+use std::mem;
+
+/// Where each symbol was last seen, as a 1-based position or 0 for never
+trait LastSeen {
+    type Symbol;
+
+    /// Records the symbol at `position`, returning where it was before
+    fn replace(&mut self, symbol: Self::Symbol, position: usize) -> usize;
+}
+
+/// A table of all 256 bytes, the fast path for byte signals
+struct Bytes([usize; 256]);
+
+impl Default for Bytes {
+    fn default() -> Bytes {
+        Bytes([0; 256])
+    }
+}
+
+impl LastSeen for Bytes {
+    type Symbol = u8;
+
+    fn replace(&mut self, symbol: u8, position: usize) -> usize {
+        mem::replace(&mut self.0[symbol as usize], position)
+    }
+}
+
+/// A table for a small alphabet, whose symbols `index` numbers from 0
+///
+/// Panics on a symbol numbered past the end of the alphabet.
+struct Indexed<T, F> {
+    last: Vec<usize>,
+    index: F,
+    symbol: PhantomData<fn(T)>,
+}
+
+impl<T, F: Fn(&T) -> usize> Indexed<T, F> {
+    fn new(alphabet: usize, index: F) -> Indexed<T, F> {
+        Indexed {
+            last: vec![0; alphabet],
+            index,
+            symbol: PhantomData,
+        }
+    }
+}
+
+impl<T, F: Fn(&T) -> usize> LastSeen for Indexed<T, F> {
+    type Symbol = T;
+
+    fn replace(&mut self, symbol: T, position: usize) -> usize {
+        mem::replace(&mut self.last[(self.index)(&symbol)], position)
+    }
+}
+
+/// Any symbols that can be hashed, however many different ones there are
+struct Hashed<T>(HashMap<T, usize>);
+
+impl<T: Eq + Hash> LastSeen for Hashed<T> {
+    type Symbol = T;
+
+    fn replace(&mut self, symbol: T, position: usize) -> usize {
+        self.0.insert(symbol, position).unwrap_or(0)
+    }
+}
+
+/// Finds a marker in a signal as it arrives, one symbol at a time
+///
+/// Keeps where each symbol was last seen and where the current run of
+/// distinct symbols starts, so every symbol costs the same whatever the window
+/// size, and nothing of the signal itself needs keeping.
+struct Detector<S: LastSeen = Bytes> {
+    size: usize,
+    seen: S,
+    start: usize,
+    read: usize,
+}
+
+impl Detector {
+    fn new(size: usize) -> Detector {
+        Detector::with(size, Bytes::default())
+    }
+}
+
+impl<S: LastSeen> Detector<S> {
+    fn with(size: usize, seen: S) -> Detector<S> {
+        Detector {
+            size,
+            seen,
+            start: 0,
+            read: 0,
+        }
+    }
+
+    /// Takes the next symbol, returning the number read so far if the last
+    /// `size` of them are all different
+    fn push(&mut self, symbol: S::Symbol) -> Option<usize> {
+        self.read += 1;
+        self.start = self.start.max(self.seen.replace(symbol, self.read));
+        (self.read - self.start >= self.size).then_some(self.read)
+    }
+
+    /// Length of the run of distinct symbols the last one read ends
+    fn run(&self) -> usize {
+        self.read - self.start
+    }
+}
+
+/// The end of the first marker of each size, all found in a single pass
+///
+/// A marker also ends markers of every smaller size, so the first ones turn
+/// up in order of size, and the pass can stop at the biggest one.
+fn first_markers<S: LastSeen>(
+    symbols: impl IntoIterator<Item = S::Symbol>,
+    sizes: &[usize],
+    seen: S,
+) -> Vec<Option<usize>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&k| sizes[k]);
+    let mut found = vec![None; sizes.len()];
+    // order[next] is the smallest size without a marker yet
+    let mut next = 0;
+    let mut detector = Detector::with(sizes.iter().max().copied().unwrap_or(0), seen);
+    let mut symbols = symbols.into_iter();
+    loop {
+        while next < order.len() && sizes[order[next]] <= detector.run() {
+            found[order[next]] = Some(detector.read);
+            next += 1;
+        }
+        if next == order.len() {
+            return found;
+        }
+        match symbols.next() {
+            Some(symbol) => detector.push(symbol),
+            None => return found,
+        };
+    }
+}
+
+/// Numbers of characters read when the last `size` of them were first all
+/// different, the end of the first marker, for each size
+fn markers(chars: &[u8], sizes: &[usize]) -> Vec<Option<usize>> {
+    first_markers(chars.iter().copied(), sizes, Bytes::default())
+}
+
+/// The first markers in any kind of symbols: chars, words, IDs...
+fn markers_of<T: Eq + Hash>(
+    symbols: impl IntoIterator<Item = T>,
+    sizes: &[usize],
+) -> Vec<Option<usize>> {
+    first_markers(symbols, sizes, Hashed(HashMap::new()))
+}
+
+/// The first markers in symbols from a small alphabet, numbered from 0 by
+/// `index`, without the cost of hashing them
+fn markers_indexed<T>(
+    symbols: impl IntoIterator<Item = T>,
+    sizes: &[usize],
+    alphabet: usize,
+    index: impl Fn(&T) -> usize,
+) -> Vec<Option<usize>> {
+    first_markers(symbols, sizes, Indexed::new(alphabet, index))
+}
+
+/// Every marker of one size in a signal, as where it ends and its symbols
+struct Markers<'a, T, S: LastSeen> {
+    symbols: &'a [T],
+    detector: Detector<S>,
+    /// Whether the empty marker at the very start is still to come
+    empty: bool,
+}
+
+impl<'a, T: Clone, S: LastSeen<Symbol = T>> Markers<'a, T, S> {
+    fn new(symbols: &'a [T], size: usize, seen: S) -> Markers<'a, T, S> {
+        Markers {
+            symbols,
+            detector: Detector::with(size, seen),
+            empty: size == 0,
+        }
+    }
+}
+
+impl<'a, T: Clone, S: LastSeen<Symbol = T>> Iterator for Markers<'a, T, S> {
+    type Item = (usize, &'a [T]);
+
+    fn next(&mut self) -> Option<(usize, &'a [T])> {
+        if self.empty {
+            self.empty = false;
+            return Some((0, &self.symbols[..0]));
+        }
+        while let Some(symbol) = self.symbols.get(self.detector.read) {
+            if let Some(end) = self.detector.push(symbol.clone()) {
+                return Some((end, &self.symbols[end - self.detector.size..end]));
             }
+        }
+        None
+    }
+}
+
+/// Every marker of one size in a byte signal
+fn every_marker(chars: &[u8], size: usize) -> Markers<'_, u8, Bytes> {
+    Markers::new(chars, size, Bytes::default())
+}
+
+/// A message in a stream, after its start-of-message marker and up to the next
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Frame<'a> {
+    /// Where the payload starts in the stream
+    offset: usize,
+    length: usize,
+    payload: &'a [u8],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FrameError {
+    /// The stream never has `size` different bytes in a row
+    NoMarker { size: usize, length: usize },
+    /// Empty markers are everywhere, so they can't separate anything
+    ZeroSize,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::NoMarker { size, length } => write!(
+                f,
+                "No marker of {} different bytes in {} bytes of stream",
+                size, length
+            ),
+            FrameError::ZeroSize => write!(f, "Markers need a size of at least 1"),
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+/// The messages of a stream, each starting after a marker
+///
+/// A new search starts after each marker, so the next one is the first run of
+/// `size` different bytes wholly within the message, and ends it. Bytes before
+/// the first marker aren't part of any message.
+struct Frames<'a> {
+    stream: &'a [u8],
+    size: usize,
+    /// Where the next message starts, unless the last one is done
+    next: Option<usize>,
+}
+
+impl<'a> Frames<'a> {
+    fn new(stream: &'a [u8], size: usize) -> Result<Frames<'a>, FrameError> {
+        if size == 0 {
+            return Err(FrameError::ZeroSize);
+        }
+        let first = markers(stream, &[size])[0].ok_or(FrameError::NoMarker {
+            size,
+            length: stream.len(),
+        })?;
+        Ok(Frames {
+            stream,
+            size,
+            next: Some(first),
         })
-        .collect()
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Frame<'a>> {
+        let offset = self.next?;
+        let rest = &self.stream[offset..];
+        let length = match markers(rest, &[self.size])[0] {
+            Some(end) => {
+                self.next = Some(offset + end);
+                end - self.size
+            }
+            None => {
+                self.next = None;
+                rest.len()
+            }
+        };
+        Some(Frame {
+            offset,
+            length,
+            payload: &rest[..length],
+        })
+    }
+}
+
+/// The end of the first marker in a stream, read in chunks of up to 8 KiB;
+/// reading stops after the chunk the marker ends in, so the reader may be
+/// left past the marker
+fn read_marker<R: Read>(mut reader: R, size: usize) -> io::Result<Option<usize>> {
+    if size == 0 {
+        return Ok(Some(0));
+    }
+    let mut detector = Detector::new(size);
+    let mut buffer = [0; 8192];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(None),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(end) = buffer[..n].iter().find_map(|&c| detector.push(c)) {
+            return Ok(Some(end));
+        }
+    }
 }
 
 fn main() {
-    let contents: &[u8] = include_str!("../input.txt").as_bytes();
-    println!("First start-of-packet marker: {}", markers(contents, 4)[0].0);
-    println!("First start-of-message marker: {}", markers(contents, 14)[0].0);
+    let show = |m: Option<usize>| m.map_or("none".to_string(), |m| m.to_string());
+
+    // `day6 FILE [SIZE]` streams a file, or standard input for "-", and
+    // stops at the first marker of that size (14 by default)
+    if let Some(path) = std::env::args().nth(1) {
+        let size = match std::env::args().nth(2).map(|s| s.parse()) {
+            Some(Ok(size)) => size,
+            Some(Err(e)) => panic!("Invalid marker size: {}", e),
+            None => 14,
+        };
+        let found = match path.as_str() {
+            "-" => read_marker(io::stdin().lock(), size),
+            _ => std::fs::File::open(&path).and_then(|f| read_marker(f, size)),
+        };
+        match found {
+            Ok(m) => println!("First marker of size {}: {}", size, show(m)),
+            Err(e) => panic!("Cannot read {}: {}", path, e),
+        }
+        return;
+    }
+
+    // the signal is normally lowercase letters, but any text will do
+    let signal = include_str!("../input.txt").trim_end();
+    let sizes = [4, 14];
+    let found = if signal.bytes().all(|b| b.is_ascii_lowercase()) {
+        markers_indexed(signal.bytes(), &sizes, 26, |b| (b - b'a') as usize)
+    } else if signal.is_ascii() {
+        markers(signal.as_bytes(), &sizes)
+    } else {
+        markers_of(signal.chars(), &sizes)
+    };
+    println!("First start-of-packet marker: {}", show(found[0]));
+    println!("First start-of-message marker: {}", show(found[1]));
+    println!(
+        "Bytes ending a start-of-message marker: {}",
+        every_marker(signal.as_bytes(), 14).count()
+    );
+    match Frames::new(signal.as_bytes(), 14) {
+        Ok(frames) => {
+            let lengths: Vec<usize> = frames.map(|frame| frame.length).collect();
+            println!(
+                "Messages: {}, {} bytes in all",
+                lengths.len(),
+                lengths.iter().sum::<usize>()
+            );
+        }
+        Err(e) => println!("Messages: none, {}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use useful::Random;
+
+    const EXAMPLES: [&str; 5] = [
+        "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+        "bvwbjplbgvbhsrlpgdmjqwftvncz",
+        "nppdvjthqldpwncqszvftbrmjlhg",
+        "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+        "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
+    ];
+
+    fn marker(chars: &[u8], size: usize) -> Option<usize> {
+        markers(chars, &[size])[0]
+    }
+
+    /// Checks every window in full
+    fn brute_force(chars: &[u8], size: usize) -> Option<usize> {
+        (size..=chars.len()).find(|&i| {
+            let window = &chars[i - size..i];
+            (0..size).all(|j| !window[..j].contains(&window[j]))
+        })
+    }
+
+    /// A reproducible signal of len letters, drawn from the first `alphabet`
+    fn random_signal(len: usize, alphabet: u8, seed: u64) -> Vec<u8> {
+        let mut random = Random::new(seed);
+        (0..len)
+            .map(|_| b'a' + random.below(alphabet as usize) as u8)
+            .collect()
+    }
 
     #[test]
     fn check_for_examples_message() {
-        let examples: &[&[u8]] = &[
-            "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
-            "bvwbjplbgvbhsrlpgdmjqwftvncz",
-            "nppdvjthqldpwncqszvftbrmjlhg",
-            "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
-            "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
-        ]
-        .map(|s| s.as_bytes()); //.collect();
-        let mks = examples.iter().map(|s| markers(s, 14)).collect::<Vec<_>>();
-        println!("{:?}", mks);
-        assert_eq!(
-            mks.iter().map(|ms| ms[0].0).collect::<Vec<_>>(),
-            [19, 23, 23, 29, 26]
-        )
+        let mks = EXAMPLES.map(|s| marker(s.as_bytes(), 14));
+        assert_eq!(mks, [19, 23, 23, 29, 26].map(Some))
     }
 
     #[test]
     fn check_for_examples_packet() {
-        let examples: &[&[u8]] = &[
-            "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
-            "bvwbjplbgvbhsrlpgdmjqwftvncz",
-            "nppdvjthqldpwncqszvftbrmjlhg",
-            "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
-            "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
-        ]
-        .map(|s| s.as_bytes()); //.collect();
-        let mks = examples.iter().map(|s| markers(s, 4)).collect::<Vec<_>>();
-        println!("{:?}", mks);
+        let mks = EXAMPLES.map(|s| marker(s.as_bytes(), 4));
+        assert_eq!(mks, [7, 5, 6, 10, 11].map(Some))
+    }
+
+    #[test]
+    fn no_marker() {
+        assert_eq!(None, marker(b"", 4));
+        assert_eq!(None, marker(b"abcabcabc", 4));
+        assert_eq!(None, marker(EXAMPLES[0].as_bytes(), 27));
+        // a marker can end the signal
+        assert_eq!(Some(4), marker(b"abcd", 4));
+        assert_eq!(Some(0), marker(b"", 0));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        // an alphabet of a few letters, so markers are rare for bigger windows
+        let signal = random_signal(2000, 8, 11);
+        for size in 0..=9 {
+            for end in [10, 100, 2000] {
+                assert_eq!(
+                    brute_force(&signal[..end], size),
+                    marker(&signal[..end], size)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn wide_window() {
+        // there are only 256 different bytes, so a window of 10,000 never
+        // matches, but it is still a single pass like any other
+        let mut signal = vec![b'x'; 1_000_000];
+        signal.extend(0..=255);
+        assert_eq!(Some(signal.len()), marker(&signal, 256));
+        assert_eq!(None, marker(&signal, 10_000));
+    }
+
+    /// Hands out a few bytes per read, like a slow pipe
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn stream_in_chunks() {
+        for example in EXAMPLES {
+            for chunk in [1, 3, 100] {
+                for size in [4, 14] {
+                    let streamed = read_marker(Trickle(example.as_bytes(), chunk), size);
+                    assert_eq!(marker(example.as_bytes(), size), streamed.unwrap());
+                }
+            }
+        }
+        assert_eq!(None, read_marker(Trickle(b"abab", 1), 3).unwrap());
+    }
+
+    #[test]
+    fn stream_stops_at_marker() {
+        // the stream never ends, so this only returns if reading stops early
+        let endless = b"aabcd".chain(io::repeat(b'x'));
+        assert_eq!(Some(5), read_marker(endless, 4).unwrap());
+
+        // a marker past the first buffer-full
+        let long = b"x".repeat(20_000);
+        assert_eq!(
+            Some(20_004),
+            read_marker(long.chain(&b"wxyz"[..]), 4).unwrap()
+        );
+    }
+
+    #[test]
+    fn stream_errors() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("unplugged"))
+            }
+        }
+        let error = read_marker(b"abab".chain(Broken), 4).unwrap_err();
+        assert_eq!("unplugged", error.to_string());
+    }
+
+    #[test]
+    fn any_symbols() {
+        // characters, not bytes
+        assert_eq!(Some(3), markers_of("éàü".chars(), &[3])[0]);
+        assert_eq!(Some(4), marker("éàü".as_bytes(), 3));
+        assert_eq!(Some(5), markers_of("ééàüa".chars(), &[4])[0]);
+        assert_eq!(None, markers_of("ééàüa".chars(), &[5])[0]);
+
+        let words = "the cat sat on the mat the end".split(' ');
+        assert_eq!(Some(6), markers_of(words.clone(), &[5])[0]);
+        assert_eq!(None, markers_of(words, &[6])[0]);
+
+        let sensors: [u32; 7] = [7001, 7002, 7001, 9000, 7003, 7002, 7004];
+        assert_eq!(Some(4), markers_of(sensors, &[3])[0]);
+        assert_eq!(Some(5), markers_of(sensors.iter(), &[4])[0]);
+    }
+
+    #[test]
+    fn fast_paths_agree() {
+        let dna: Vec<char> = (random_signal(1000, 4, 3).iter())
+            .map(|&b| ['A', 'C', 'G', 'T'][(b - b'a') as usize])
+            .collect();
+        let index = |c: &char| "ACGT".find(*c).unwrap();
+        let bytes: Vec<u8> = dna.iter().map(|&c| c as u8).collect();
+        for size in 0..=5 {
+            let expected = brute_force(&bytes, size);
+            assert_eq!(expected, marker(&bytes, size));
+            assert_eq!(expected, markers_of(dna.iter(), &[size])[0]);
+            assert_eq!(
+                expected,
+                markers_indexed(dna.iter().copied(), &[size], 4, index)[0]
+            );
+        }
+        for example in EXAMPLES {
+            let letter = |b: &u8| (b - b'a') as usize;
+            assert_eq!(
+                marker(example.as_bytes(), 14),
+                markers_indexed(example.bytes(), &[14], 26, letter)[0]
+            );
+        }
+    }
+
+    #[test]
+    fn many_sizes_in_one_pass() {
+        for example in EXAMPLES {
+            let chars = example.as_bytes();
+            assert_eq!(
+                vec![
+                    marker(chars, 14),
+                    marker(chars, 4),
+                    Some(0),
+                    None,
+                    marker(chars, 4)
+                ],
+                markers(chars, &[14, 4, 0, 100, 4])
+            );
+        }
+        assert_eq!(Vec::<Option<usize>>::new(), markers(b"abc", &[]));
+
+        let signal = random_signal(3000, 10, 5);
+        let sizes = [9, 2, 7, 11, 5, 3, 10, 8];
+        let expected: Vec<Option<usize>> = sizes.iter().map(|&n| brute_force(&signal, n)).collect();
+        assert_eq!(expected, markers(&signal, &sizes));
+    }
+
+    #[test]
+    fn every_marker_with_its_window() {
+        let found: Vec<(usize, &[u8])> = every_marker(b"aabcabcdd", 3).collect();
+        assert_eq!(
+            vec![
+                (4, &b"abc"[..]),
+                (5, b"bca"),
+                (6, b"cab"),
+                (7, b"abc"),
+                (8, b"bcd")
+            ],
+            found
+        );
+        assert_eq!(
+            vec![0, 1, 2],
+            every_marker(b"ab", 0)
+                .map(|(end, _)| end)
+                .collect::<Vec<_>>()
+        );
+
+        // every position brute force finds, and nothing else
+        for example in EXAMPLES {
+            let chars = example.as_bytes();
+            for size in [4, 14] {
+                let expected: Vec<usize> = (size..=chars.len())
+                    .filter(|&end| brute_force(&chars[end - size..end], size).is_some())
+                    .collect();
+                let found: Vec<usize> = every_marker(chars, size)
+                    .map(|(end, window)| {
+                        assert_eq!(&chars[end - size..end], window);
+                        end
+                    })
+                    .collect();
+                assert_eq!(expected, found);
+            }
+        }
+
+        let words: Vec<&str> = "to be or not to be".split(' ').collect();
+        let found: Vec<(usize, &[&str])> =
+            Markers::new(&words, 4, Hashed(HashMap::new())).collect();
+        assert_eq!(
+            vec![
+                (4, &["to", "be", "or", "not"][..]),
+                (5, &["be", "or", "not", "to"][..]),
+                (6, &["or", "not", "to", "be"][..])
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn frames_between_markers() {
+        // junk, a marker, a message, a marker and a last message
+        let stream: Vec<u8> = "www wxyz hheelaa abcd ddxx"
+            .bytes()
+            .filter(|&b| b != b' ')
+            .collect();
+        let frames: Vec<Frame> = Frames::new(&stream, 4).unwrap().collect();
+        assert_eq!(
+            vec![
+                Frame {
+                    offset: 7,
+                    length: 7,
+                    payload: b"hheelaa"
+                },
+                Frame {
+                    offset: 18,
+                    length: 4,
+                    payload: b"ddxx"
+                },
+            ],
+            frames
+        );
+
+        // markers back to back, the last one ending the stream
+        let frames: Vec<(usize, usize)> = Frames::new(b"abcdefgh", 4)
+            .unwrap()
+            .map(|frame| (frame.offset, frame.length))
+            .collect();
+        assert_eq!(vec![(4, 0), (8, 0)], frames);
+
+        let first = Frames::new(EXAMPLES[0].as_bytes(), 14).unwrap().next();
+        assert_eq!(Some(19), first.map(|frame| frame.offset));
+    }
+
+    #[test]
+    fn frames_without_markers() {
+        assert_eq!(
+            Some(FrameError::NoMarker { size: 4, length: 9 }),
+            Frames::new(b"abcabcabc", 4).err()
+        );
         assert_eq!(
-            mks.iter().map(|ms| ms[0].0).collect::<Vec<_>>(),
-            [7, 5, 6, 10, 11]
-        )
+            "No marker of 4 different bytes in 9 bytes of stream",
+            Frames::new(b"abcabcabc", 4).err().unwrap().to_string()
+        );
+        assert_eq!(Some(FrameError::ZeroSize), Frames::new(b"abc", 0).err());
     }
 }
@@ -0,0 +1,179 @@
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// Closed range of integers, `start..=end`, as written "a-b" in the puzzles
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Interval {
+    pub fn new(start: usize, end: usize) -> Interval {
+        if start > end {
+            panic!("Interval start ({}) is after its end ({})", start, end);
+        }
+        Interval { start, end }
+    }
+
+    /// Number of integers in the interval
+    pub fn length(&self) -> usize {
+        self.end - self.start + 1
+    }
+
+    pub fn contains(&self, x: usize) -> bool {
+        self.start <= x && x <= self.end
+    }
+
+    /// Whether `other` lies entirely inside this interval
+    pub fn covers(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        self.overlaps(other)
+            .then(|| Interval::new(self.start.max(other.start), self.end.min(other.end)))
+    }
+
+    /// The single interval covering both, if they overlap or touch end to end
+    pub fn union(&self, other: &Interval) -> Option<Interval> {
+        (self.start <= other.end.saturating_add(1) && other.start <= self.end.saturating_add(1))
+            .then(|| Interval::new(self.start.min(other.start), self.end.max(other.end)))
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseIntervalError {
+    MissingDash(String),
+    Bound(ParseIntError),
+    Reversed(usize, usize),
+}
+
+impl fmt::Display for ParseIntervalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseIntervalError::MissingDash(s) => write!(f, "No '-' in interval \"{}\"", s),
+            ParseIntervalError::Bound(e) => write!(f, "Invalid interval bound: {}", e),
+            ParseIntervalError::Reversed(a, b) => {
+                write!(f, "Interval start ({}) is after its end ({})", a, b)
+            }
+        }
+    }
+}
+
+impl Error for ParseIntervalError {}
+
+impl From<ParseIntError> for ParseIntervalError {
+    fn from(e: ParseIntError) -> ParseIntervalError {
+        ParseIntervalError::Bound(e)
+    }
+}
+
+impl FromStr for Interval {
+    type Err = ParseIntervalError;
+
+    fn from_str(s: &str) -> Result<Interval, ParseIntervalError> {
+        let (a, b) = s
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| ParseIntervalError::MissingDash(s.to_string()))?;
+        let (start, end) = (a.parse()?, b.parse()?);
+        if start > end {
+            return Err(ParseIntervalError::Reversed(start, end));
+        }
+        Ok(Interval { start, end })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const MAX: usize = 12;
+
+    // every interval within 0..=MAX, checked against the set of integers it holds
+    fn intervals() -> impl Iterator<Item = Interval> {
+        (0..=MAX).flat_map(|a| (a..=MAX).map(move |b| Interval::new(a, b)))
+    }
+
+    fn set(i: &Interval) -> BTreeSet<usize> {
+        (i.start..=i.end).collect()
+    }
+
+    fn from_set(s: &BTreeSet<usize>) -> Option<Interval> {
+        let (&a, &b) = (s.first()?, s.last()?);
+        (s.len() == b - a + 1).then(|| Interval::new(a, b))
+    }
+
+    #[test]
+    fn length_and_contains_match_sets() {
+        for i in intervals() {
+            assert_eq!(set(&i).len(), i.length());
+            for x in 0..=MAX + 1 {
+                assert_eq!(set(&i).contains(&x), i.contains(x), "{} contains {}", i, x);
+            }
+        }
+    }
+
+    #[test]
+    fn pairwise_operations_match_sets() {
+        for a in intervals() {
+            for b in intervals() {
+                let (sa, sb) = (set(&a), set(&b));
+                assert_eq!(sb.is_subset(&sa), a.covers(&b), "{} covers {}", a, b);
+                assert_eq!(!sa.is_disjoint(&sb), a.overlaps(&b), "{} overlaps {}", a, b);
+                assert_eq!(
+                    from_set(&sa.intersection(&sb).copied().collect()),
+                    a.intersection(&b),
+                    "{} & {}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    from_set(&sa.union(&sb).copied().collect()),
+                    a.union(&b),
+                    "{} | {}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_round_trips() {
+        for i in intervals() {
+            assert_eq!(Ok(i), i.to_string().parse());
+        }
+        assert_eq!(Ok(Interval::new(2, 4)), " 2-4 ".parse());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(ParseIntervalError::MissingDash("24".into())),
+            "24".parse::<Interval>()
+        );
+        assert_eq!(
+            Err(ParseIntervalError::Reversed(4, 2)),
+            "4-2".parse::<Interval>()
+        );
+        assert!(matches!(
+            "2-x".parse::<Interval>(),
+            Err(ParseIntervalError::Bound(_))
+        ));
+    }
+}
@@ -1,3 +1,9 @@
+mod interval;
+mod random;
+
+pub use interval::{Interval, ParseIntervalError};
+pub use random::Random;
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
@@ -0,0 +1,42 @@
+/// Seeded pseudo-random numbers, the same for every run, for tests that want
+/// more inputs than can be written out by hand
+///
+/// A 64-bit linear congruential generator, with Knuth's MMIX constants, whose
+/// high bits are kept since its low bits repeat quickly.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /// The next number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) as usize % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let draw = |seed| {
+            let mut random = Random::new(seed);
+            (0..100).map(|_| random.below(10)).collect::<Vec<usize>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+        assert!(draw(7).iter().all(|&n| n < 10));
+        // every value comes up
+        assert!((0..10).all(|n| draw(7).contains(&n)));
+    }
+}